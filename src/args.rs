@@ -1,55 +1,323 @@
-use std::env;
+use std::{env, num::NonZeroUsize, path::PathBuf};
 
-/// A custom iterator to parse the arguments.
-/// - IntoIterator is implemented as the Iterator of the positional arguments.
+use crate::rusk::{is_valid_arg_name, Target};
+
+/// Usage of the command.
+pub const HELP: &str = "\
+Usage: rusk [OPTIONS] [TASK [NAME=VALUE]...]... [-- ARGS...]
+
+Runs the given tasks. When no task is given, lists the available tasks.
+
+Arguments:
+  TASK              Task to be executed
+  NAME=VALUE        Argument of the preceding task
+  -- ARGS...        Arguments passed to the target tasks as $RUSK_ARGS
+
+Options:
+  -C, --directory <DIR>  Change to DIR before doing anything
+  -f, --file <FILE>      Load FILE instead of searching for rusk.toml files
+  -j, --jobs <N>         Run at most N scripts at once [env: RUSK_JOBS]
+  -k, --keep-going       Keep executing independent tasks after a failure
+  -n, --dry-run          Print the execution plan without executing scripts
+  -w, --watch            Rerun the tasks whenever their source files change
+      --clean            Remove the files generated by the tasks (or the given tasks)
+  -l, --list             List the available tasks
+      --format <FORMAT>  Format of the list: text, json or ndjson [default: text]
+      --graph[=FORMAT]   Print the dependency graph in dot or mermaid [default: dot]
+      --check            Check the rusk.toml files for problems without running anything
+      --schema           Print the JSON Schema of rusk.toml
+  -h, --help             Print help
+  -V, --version          Print version
+";
+
+/// Operation mode selected by the options.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// Execute the targets
+    Run,
+    /// List the available tasks
+    List,
+    /// Remove the generated files
+    Clean,
+    /// Print the dependency graph
+    Graph,
+    /// Report the problems of the configuration
+    Check,
+    /// Print the JSON Schema of rusk.toml
+    Schema,
+    /// Print help
+    Help,
+    /// Print version
+    Version,
+}
+
+/// Output format of the task list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ListFormat {
+    /// Human-readable text, aligned on a terminal and tab-separated otherwise
+    #[default]
+    Text,
+    /// Single JSON document
+    Json,
+    /// JSON object per line
+    Ndjson,
+}
+
+/// Output format of the dependency graph.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GraphFormat {
+    /// Graphviz DOT
+    #[default]
+    Dot,
+    /// Mermaid flowchart
+    Mermaid,
+}
+
+/// Parsed command-line arguments.
+/// - IntoIterator is implemented as the Iterator of the targets.
 pub struct Args {
-    iter: PositionalArgsIter,
+    /// Operation mode
+    pub mode: Mode,
+    /// Directory to change to before doing anything
+    pub directory: Option<PathBuf>,
+    /// Config files to load instead of searching
+    pub files: Vec<PathBuf>,
+    /// Print the execution plan instead of executing
+    pub dry_run: bool,
+    /// Maximum number of scripts running at once
+    pub jobs: Option<NonZeroUsize>,
+    /// Keep executing independent tasks after a failure
+    pub keep_going: bool,
+    /// Rerun the targets whenever their source files change
+    pub watch: bool,
+    /// Output format of the task list
+    pub format: ListFormat,
+    /// Output format of the dependency graph
+    pub graph_format: GraphFormat,
+    /// Tasks to be executed with their parameters
+    targets: Vec<Target>,
+    /// Arguments after `--`, passed through to the target tasks
+    extra: Vec<String>,
+}
+
+/// Error when parsing the command-line arguments.
+#[derive(Debug, thiserror::Error)]
+pub enum ArgsError {
+    /// `NAME=VALUE` appeared before any task name
+    #[error("Parameter {0:?} must follow a task name")]
+    OrphanParameter(String),
+    /// Option not known
+    #[error("Unknown option {0:?}. See `rusk --help`")]
+    UnknownOption(String),
+    /// Option requires a value but it is not given
+    #[error("Option {0} requires a value")]
+    MissingValue(&'static str),
+    /// Option takes no value but it is given
+    #[error("Option {0} takes no value")]
+    UnexpectedValue(&'static str),
+    /// Number of jobs is not a positive integer
+    #[error("Number of jobs must be a positive integer, but got {0:?}")]
+    InvalidJobs(String),
+    /// Format of the list is not known
+    #[error("Format must be one of text, json or ndjson, but got {0:?}")]
+    InvalidFormat(String),
+    /// Format of the graph is not known
+    #[error("Graph format must be either dot or mermaid, but got {0:?}")]
+    InvalidGraphFormat(String),
+    /// Options which cannot be used together
+    #[error("Option {0} cannot be combined with {1}")]
+    Conflict(&'static str, &'static str),
+    /// Option which works only with the tasks to run
+    #[error("Option {0} requires a task to run")]
+    MissingTask(&'static str),
+}
+
+/// Short options taking a value, which ends a group of short flags
+const SHORT_WITH_VALUE: [&str; 3] = ["-C", "-f", "-j"];
+
+/// Environment variable to give the number of jobs
+pub const JOBS_ENV: &str = "RUSK_JOBS";
+
+/// Parses the number of jobs.
+pub fn parse_jobs(value: &str) -> Result<NonZeroUsize, ArgsError> {
+    value
+        .parse()
+        .map_err(|_| ArgsError::InvalidJobs(value.to_owned()))
 }
 
 impl Args {
-    /// Creates a new Args iterator.
-    pub fn new() -> Self {
-        let mut inner = env::args();
-        inner.next(); // Skip the first argument
-        let first = inner.next();
-        Self {
-            iter: PositionalArgsIter {
-                inner,
-                first,
-                first_read: false,
-            },
+    /// Parses the arguments of the current process.
+    pub fn new() -> Result<Self, ArgsError> {
+        Self::parse(env::args().skip(1))
+    }
+    /// Parses the arguments.
+    /// - Options start with `-` and may appear anywhere before `--`.
+    /// - `NAME=VALUE` is a parameter of the preceding task.
+    /// - Everything after `--` is collected as extra arguments.
+    fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, ArgsError> {
+        let mut parsed = Self {
+            mode: Mode::Run,
+            directory: None,
+            files: Vec::new(),
+            dry_run: false,
+            jobs: None,
+            keep_going: false,
+            watch: false,
+            format: ListFormat::default(),
+            graph_format: GraphFormat::default(),
+            targets: Vec::new(),
+            extra: Vec::new(),
+        };
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            if arg == "--" {
+                parsed.extra.extend(args.by_ref());
+                break;
+            }
+            if arg.starts_with('-') && arg != "-" {
+                parsed.parse_option(arg, &mut args)?;
+                continue;
+            }
+            if let Some((name, value)) = arg.split_once('=') {
+                if is_valid_arg_name(name) {
+                    let Some(target) = parsed.targets.last_mut() else {
+                        return Err(ArgsError::OrphanParameter(arg));
+                    };
+                    target.params.push((name.to_owned(), value.to_owned()));
+                    continue;
+                }
+            }
+            parsed.targets.push(Target {
+                name: arg,
+                params: Vec::new(),
+            });
+        }
+        if parsed.watch {
+            let conflict = match parsed.mode {
+                Mode::Run if parsed.dry_run => Some("--dry-run"),
+                Mode::Clean => Some("--clean"),
+                Mode::Graph => Some("--graph"),
+                Mode::Check => Some("--check"),
+                Mode::List => Some("--list"),
+                Mode::Schema => Some("--schema"),
+                Mode::Run | Mode::Help | Mode::Version => None,
+            };
+            if let Some(other) = conflict {
+                return Err(ArgsError::Conflict("--watch", other));
+            }
+            if parsed.mode == Mode::Run && parsed.targets.is_empty() {
+                return Err(ArgsError::MissingTask("--watch"));
+            }
         }
+        if parsed.mode == Mode::Run && parsed.targets.is_empty() {
+            parsed.mode = Mode::List;
+        }
+        Ok(parsed)
     }
-    /// Whether or not there are no positional arguments.
-    pub fn no_pargs(&self) -> bool {
-        self.iter.first.is_none()
+    /// Parses an option, taking its value from `rest` if needed.
+    /// Both `--opt VALUE` and `--opt=VALUE` (or `-o VALUE` and `-oVALUE`) are accepted.
+    /// Short flags may be grouped as in `-kn`, where only the last one may take a value as in `-kj4`.
+    fn parse_option(
+        &mut self,
+        arg: String,
+        rest: &mut impl Iterator<Item = String>,
+    ) -> Result<(), ArgsError> {
+        if let Some(long) = arg.strip_prefix("--") {
+            return match long.split_once('=') {
+                Some((name, value)) => {
+                    self.apply_option(&format!("--{name}"), Some(value.to_owned()), rest)
+                }
+                None => self.apply_option(&arg, None, rest),
+            };
+        }
+        for (i, c) in arg.char_indices().skip(1) {
+            let name = format!("-{c}");
+            if SHORT_WITH_VALUE.contains(&name.as_str()) {
+                let value = &arg[i + c.len_utf8()..];
+                let inline = (!value.is_empty()).then(|| value.to_owned());
+                return self.apply_option(&name, inline, rest);
+            }
+            self.apply_option(&name, None, rest)?;
+        }
+        Ok(())
     }
-}
-
-impl IntoIterator for Args {
-    type Item = String;
-    type IntoIter = PositionalArgsIter;
+    /// Applies an option with its inline value, taking the value from `rest` if needed.
+    fn apply_option(
+        &mut self,
+        name: &str,
+        inline: Option<String>,
+        rest: &mut impl Iterator<Item = String>,
+    ) -> Result<(), ArgsError> {
+        /// Takes the value of the option
+        macro_rules! value {
+            ($opt: expr) => {
+                match inline {
+                    Some(value) => value,
+                    None => rest.next().ok_or(ArgsError::MissingValue($opt))?,
+                }
+            };
+        }
+        /// Sets the flag, rejecting any value
+        macro_rules! flag {
+            ($opt: expr, $set: expr) => {{
+                if inline.is_some() {
+                    return Err(ArgsError::UnexpectedValue($opt));
+                }
+                $set;
+            }};
+        }
 
-    fn into_iter(self) -> Self::IntoIter {
-        self.iter
+        match name {
+            "-C" | "--directory" => self.directory = Some(value!("--directory").into()),
+            "-f" | "--file" => self.files.push(value!("--file").into()),
+            "-j" | "--jobs" => self.jobs = Some(parse_jobs(&value!("--jobs"))?),
+            "-k" | "--keep-going" => flag!("--keep-going", self.keep_going = true),
+            "-n" | "--dry-run" => flag!("--dry-run", self.dry_run = true),
+            "-w" | "--watch" => flag!("--watch", self.watch = true),
+            "-l" | "--list" => flag!("--list", self.set_mode(Mode::List)),
+            "--format" => {
+                self.format = match value!("--format").as_str() {
+                    "text" => ListFormat::Text,
+                    "json" => ListFormat::Json,
+                    "ndjson" => ListFormat::Ndjson,
+                    format => return Err(ArgsError::InvalidFormat(format.to_owned())),
+                }
+            }
+            "--clean" => flag!("--clean", self.set_mode(Mode::Clean)),
+            "--check" => flag!("--check", self.set_mode(Mode::Check)),
+            "--schema" => flag!("--schema", self.set_mode(Mode::Schema)),
+            // The value is optional, so it is taken only in the form of `--graph=FORMAT`
+            "--graph" => {
+                self.graph_format = match inline.as_deref() {
+                    None | Some("dot") => GraphFormat::Dot,
+                    Some("mermaid") => GraphFormat::Mermaid,
+                    Some(format) => return Err(ArgsError::InvalidGraphFormat(format.to_owned())),
+                };
+                self.set_mode(Mode::Graph);
+            }
+            "-h" | "--help" => flag!("--help", self.set_mode(Mode::Help)),
+            "-V" | "--version" => flag!("--version", self.set_mode(Mode::Version)),
+            _ => return Err(ArgsError::UnknownOption(name.to_owned())),
+        }
+        Ok(())
+    }
+    /// Sets the mode. Help and version take precedence over the others.
+    fn set_mode(&mut self, mode: Mode) {
+        if !matches!(self.mode, Mode::Help | Mode::Version) {
+            self.mode = mode;
+        }
+    }
+    /// Take the arguments after `--`.
+    pub fn take_extra(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.extra)
     }
 }
 
-/// An iterator over the positional arguments.
-pub struct PositionalArgsIter {
-    inner: env::Args,
-    first: Option<String>,
-    first_read: bool,
-}
-
-impl Iterator for PositionalArgsIter {
-    type Item = String;
+impl IntoIterator for Args {
+    type Item = Target;
+    type IntoIter = std::vec::IntoIter<Target>;
 
-    fn next(&mut self) -> Option<Self::Item> {
-        if !self.first_read {
-            self.first_read = true;
-            return self.first.take();
-        }
-        self.inner.next()
+    fn into_iter(self) -> Self::IntoIter {
+        self.targets.into_iter()
     }
 }
@@ -0,0 +1,86 @@
+//! Health check of the configuration, reporting the problems without executing anything.
+
+use std::fmt::{Display, Formatter, Result};
+
+use colored::Colorize;
+use itertools::Itertools;
+
+use crate::{
+    fs::{Composed, RuskfileComposer},
+    rusk::{Rusk, UnknownFields},
+};
+
+/// How serious a problem is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// Likely to be a mistake, but the tasks can run
+    Warning,
+    /// The tasks involved cannot run
+    Error,
+}
+
+/// Problem found in the configuration
+pub struct Finding {
+    /// How serious the problem is
+    pub severity: Severity,
+    /// Rendered message with its location
+    message: String,
+}
+
+impl Finding {
+    /// Problem preventing the tasks from running
+    pub fn error(message: impl Display) -> Self {
+        Self {
+            severity: Severity::Error,
+            message: message.to_string(),
+        }
+    }
+    /// Problem which is likely to be a mistake
+    pub fn warning(message: impl Display) -> Self {
+        Self {
+            severity: Severity::Warning,
+            message: message.to_string(),
+        }
+    }
+}
+
+impl Display for Finding {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        ////////////////////////////////////////////////
+        //
+        // Format:
+        //     (error|warning): (message)
+        //
+        ////////////////////////////////////////////////
+
+        let title = match self.severity {
+            Severity::Warning => "warning".on_yellow().black().bold(),
+            Severity::Error => "error".bold().red(),
+        };
+        write!(f, "{title}: {}", self.message)
+    }
+}
+
+/// Check all the loaded config files and the tasks defined in them
+pub fn check(composer: RuskfileComposer) -> Vec<Finding> {
+    let mut findings = composer
+        .errors_list()
+        .sorted()
+        .map(|err| Finding::error(format_args!("Cannot load {}", err.into_verbose())))
+        .collect::<Vec<_>>();
+    let settings = composer.settings();
+    let mode = settings.unknown_fields.unwrap_or_default();
+    let Composed {
+        tasks,
+        rules,
+        errors,
+        unknown_fields,
+    } = composer.compose();
+    findings.extend(errors.into_iter().map(Finding::error));
+    findings.extend(unknown_fields.into_iter().map(|err| match mode {
+        UnknownFields::Warn => Finding::warning(err),
+        UnknownFields::Error => Finding::error(err),
+    }));
+    findings.extend(Rusk::new(tasks, rules, settings).check());
+    findings
+}
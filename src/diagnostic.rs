@@ -0,0 +1,334 @@
+//! Errors shown with their locations in the rusk.toml files.
+
+use std::{
+    fmt::{Debug, Display, Formatter, Result},
+    ops::{Deref, Range},
+    path::Path,
+    rc::Rc,
+};
+
+use toml_edit::{ImDocument, Item};
+
+use crate::{
+    path::NormarizedPath,
+    taskkey::{TaskKey, TaskKeyRelative},
+};
+
+/// Step of the path to a value in a TOML document
+#[derive(Clone, Copy)]
+pub enum Step<'a> {
+    /// Key of a table
+    Key(&'a str),
+    /// Index of an array
+    Index(usize),
+    /// First key present among the aliases of a field
+    Keys(&'a [&'a str]),
+}
+
+/// Field of the task arguments with its alias
+pub const ARGS: Step = Step::Keys(&["args", "params"]);
+/// Field of the task dependencies
+pub const DEPENDS: Step = Step::Key("depends");
+/// Field of the task inputs with its alias
+pub const INPUTS: Step = Step::Keys(&["inputs", "sources"]);
+
+/// Text of a loaded rusk.toml, kept to locate the errors found after loading
+#[derive(Clone)]
+pub struct ConfigSource(Rc<SourceFile>);
+
+/// Content of ConfigSource, shared by the tasks defined in the file
+pub struct SourceFile {
+    /// Path of the rusk.toml
+    path: NormarizedPath,
+    /// Content of the rusk.toml
+    text: String,
+}
+
+impl Deref for ConfigSource {
+    type Target = SourceFile;
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl ConfigSource {
+    pub fn new(path: NormarizedPath, text: String) -> Self {
+        Self(Rc::new(SourceFile { path, text }))
+    }
+    /// Path of the rusk.toml
+    pub fn path(&self) -> &NormarizedPath {
+        &self.path
+    }
+    /// Span of the byte range in the file
+    pub fn span(&self, range: Range<usize>) -> Span {
+        Span {
+            source: self.clone(),
+            range,
+        }
+    }
+    /// Parse the file to walk through the keys with their spans
+    pub fn document(&self) -> Option<ImDocument<&str>> {
+        ImDocument::parse(self.text.as_str()).ok()
+    }
+    /// Locate the value at the path. If not found, the nearest ancestor is located instead.
+    /// Tables are located at their keys, and the other values at themselves.
+    pub fn locate(&self, path: &[Step]) -> Option<Span> {
+        // NOTE: Parsed again only when an error is shown, so that loading is not slowed down
+        let doc = self.document()?;
+        let mut item = doc.as_item();
+        let mut range = None;
+        for step in path {
+            let (next, next_range) = match *step {
+                Step::Key(_) | Step::Keys(_) => {
+                    let keys = match step {
+                        Step::Key(key) => std::slice::from_ref(key),
+                        Step::Keys(keys) => keys,
+                        Step::Index(_) => unreachable!(),
+                    };
+                    let Some((key, value)) = item
+                        .as_table_like()
+                        .and_then(|table| keys.iter().find_map(|key| table.get_key_value(key)))
+                    else {
+                        break;
+                    };
+                    let range = match value {
+                        Item::Value(value) => value.span(),
+                        _ => None,
+                    };
+                    (value, range.or_else(|| key.span()))
+                }
+                Step::Index(index) => {
+                    let Some(value) = item.get(index) else {
+                        break;
+                    };
+                    (value, value.span())
+                }
+            };
+            item = next;
+            range = next_range.or(range);
+        }
+        Some(self.span(range?))
+    }
+}
+
+/// Location in a rusk.toml
+#[derive(Clone)]
+pub struct Span {
+    /// File containing the span
+    source: ConfigSource,
+    /// Byte range in the file
+    range: Range<usize>,
+}
+
+impl Span {
+    /// Line and column numbers of the start, both 1-based, with the text of the line
+    fn line_col(&self) -> (usize, usize, &str) {
+        let text = self.source.text.as_str();
+        let start = self.range.start.min(text.len());
+        let line_start = text[..start].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = text[start..].find('\n').map_or(text.len(), |i| start + i);
+        let line = text[..start].matches('\n').count() + 1;
+        let col = text[line_start..start].chars().count() + 1;
+        (line, col, text[line_start..line_end].trim_end_matches('\r'))
+    }
+    /// Width of the line numbers shown in the snippet
+    fn gutter_width(&self) -> usize {
+        self.line_col().0.to_string().len()
+    }
+    /// Narrow the span down to the line of the multi-line string value.
+    /// The span is kept as it is if the value is not a multi-line string.
+    pub fn line_of_string(self, index: usize) -> Self {
+        let text = &self.source.text[self.range.clone()];
+        let Some(body) = text
+            .strip_prefix("\"\"\"")
+            .or_else(|| text.strip_prefix("'''"))
+        else {
+            return self;
+        };
+        // A newline right after the delimiter is not a part of the string
+        let skipped = text.len() - body.len() + usize::from(body.starts_with('\n'));
+        let mut start = self.range.start + skipped;
+        for (i, line) in self.source.text[start..self.range.end]
+            .split('\n')
+            .enumerate()
+        {
+            if i == index {
+                let range = start..start + line.trim_end_matches('\r').len();
+                return self.source.span(range);
+            }
+            start += line.len() + 1;
+        }
+        self
+    }
+    /// Display as `path:line:col`
+    pub fn location(&self) -> impl Display + '_ {
+        struct Location<'a>(&'a Span);
+        impl Display for Location<'_> {
+            fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+                let (line, col, _) = self.0.line_col();
+                write!(f, "{}:{line}:{col}", self.0.source.path.as_short_str())
+            }
+        }
+        Location(self)
+    }
+}
+
+impl Debug for Span {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        Display::fmt(&self.location(), f)
+    }
+}
+
+impl Display for Span {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        ////////////////////////////////////////////////
+        //
+        // Format:
+        //       --> (path):(line):(col)
+        //        |
+        //     (line) | (text)
+        //        |   ^^^^
+        //
+        ////////////////////////////////////////////////
+
+        let (line, col, text) = self.line_col();
+        let width = self.gutter_width();
+        let marker = text
+            .chars()
+            .skip(col - 1)
+            .take(self.range.len())
+            .count()
+            .max(1);
+        // NOTE: Not colored, since load errors are rendered once and also printed in JSON
+        writeln!(f, "{:width$}--> {}", "", self.location())?;
+        writeln!(f, "{:width$} |", "")?;
+        writeln!(f, "{line} | {text}")?;
+        write!(
+            f,
+            "{:width$} | {:col$}{}",
+            "",
+            "",
+            "^".repeat(marker),
+            col = col - 1
+        )
+    }
+}
+
+/// Error with its location in a rusk.toml and a hint to fix it
+#[derive(Debug, Clone)]
+pub struct Diagnostic<E>(Box<Detail<E>>);
+
+/// Content of Diagnostic, boxed to keep the results small
+#[derive(Debug, Clone)]
+struct Detail<E> {
+    /// Error
+    error: E,
+    /// Location of the cause. None if unknown
+    span: Option<Span>,
+    /// Hint to fix the error
+    hint: Option<String>,
+}
+
+impl<E> Diagnostic<E> {
+    /// Error located at the span
+    pub fn new(error: E, span: Option<Span>) -> Self {
+        Self(Box::new(Detail {
+            error,
+            span,
+            hint: None,
+        }))
+    }
+    /// Add a hint to fix the error
+    pub fn with_hint(mut self, hint: impl Into<String>) -> Self {
+        self.0.hint = Some(hint.into());
+        self
+    }
+}
+
+impl<E> From<E> for Diagnostic<E> {
+    fn from(error: E) -> Self {
+        Self::new(error, None)
+    }
+}
+
+impl<E: Display> Display for Diagnostic<E> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        let Detail { error, span, hint } = self.0.as_ref();
+        write!(f, "{error}")?;
+        if let Some(span) = span {
+            write!(f, "\n{span}")?;
+        }
+        if let Some(hint) = hint {
+            // Aligned with the bars of the snippet
+            let width = span.as_ref().map_or(0, Span::gutter_width);
+            write!(f, "\n{:width$} = hint: {hint}", "")?;
+        }
+        Ok(())
+    }
+}
+
+impl<E: std::error::Error> std::error::Error for Diagnostic<E> {}
+
+/// Where a task is defined
+#[derive(Clone)]
+pub struct TaskOrigin {
+    /// rusk.toml defining the task
+    source: ConfigSource,
+    /// Name of the task as written in the rusk.toml
+    key: String,
+}
+
+impl TaskOrigin {
+    pub fn new(source: ConfigSource, key: &TaskKeyRelative) -> Self {
+        Self {
+            source,
+            key: key.as_ref().to_owned(),
+        }
+    }
+    /// Path of the rusk.toml defining the task
+    pub fn config(&self) -> &NormarizedPath {
+        self.source.path()
+    }
+    /// Locate the field of the task. The task itself is located if the path is empty.
+    pub fn locate(&self, path: &[Step]) -> Option<Span> {
+        let task = [Step::Key("tasks"), Step::Key(&self.key)];
+        self.source.locate(&[&task[..], path].concat())
+    }
+    /// Locate the entry of `depends` or `inputs` which resolves to the dependency
+    pub fn locate_dependency(&self, dep: &TaskKey) -> Option<Span> {
+        self.locate_entry(&["depends", "inputs", "sources"], dep)
+            // Expanded from a glob pattern or substituted in a pattern rule
+            .or_else(|| self.locate(&[DEPENDS]))
+    }
+    /// Locate the entry of `outputs` which resolves to the additional output
+    pub fn locate_output(&self, output: &TaskKey) -> Option<Span> {
+        self.locate_entry(&["outputs"], output)
+            .or_else(|| self.locate(&[Step::Key("outputs")]))
+    }
+    /// Locate the entry of the array fields which resolves to the task
+    fn locate_entry(&self, fields: &[&str], key: &TaskKey) -> Option<Span> {
+        let base = Path::parent(self.config()).unwrap(); // NOTE: rusk.toml is a file, so it has a parent directory
+        let doc = self.source.document()?;
+        let task = doc.as_item().get("tasks")?.get(&self.key)?;
+        for &field in fields {
+            let Some(array) = task.get(field).and_then(Item::as_array) else {
+                continue;
+            };
+            for (i, value) in array.iter().enumerate() {
+                // Only the dependencies may be phony tasks
+                let resolved = value.as_str().and_then(|value| match field {
+                    "depends" => Some(
+                        TaskKeyRelative::try_from(value.to_owned())
+                            .ok()?
+                            .into_task_key(base),
+                    ),
+                    _ => Some(TaskKey::File(base.join(value).into())),
+                });
+                if resolved.as_ref() == Some(key) {
+                    return self.locate(&[Step::Key(field), Step::Index(i)]);
+                }
+            }
+        }
+        None
+    }
+}
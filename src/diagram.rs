@@ -0,0 +1,206 @@
+//! Export of the dependency graph to Graphviz DOT and Mermaid.
+
+use std::fmt::{Display, Formatter, Result};
+
+use itertools::Itertools;
+
+use crate::{path::NormarizedPath, taskkey::TaskKey};
+
+/// Dependency graph to be drawn
+pub struct Diagram {
+    /// Nodes, dependencies first
+    pub nodes: Vec<DiagramNode>,
+    /// Edges from the dependent to the dependency, as indices of the nodes
+    pub edges: Vec<(usize, usize)>,
+}
+
+/// Node of the dependency graph
+pub struct DiagramNode {
+    /// TaskKey
+    pub key: TaskKey,
+    /// Kind of the node
+    pub kind: NodeKind,
+    /// rusk.toml defining the task. None for a file without a task
+    pub config: Option<NormarizedPath>,
+    /// Whether the task is up to date, or the file without a task exists
+    pub up_to_date: bool,
+}
+
+/// Kind of the node in the dependency graph
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum NodeKind {
+    /// Phony task
+    Phony,
+    /// File task
+    File,
+    /// File without a task
+    Source,
+}
+
+impl NodeKind {
+    /// Name used as the class of the node
+    fn as_str(self) -> &'static str {
+        match self {
+            NodeKind::Phony => "phony",
+            NodeKind::File => "file",
+            NodeKind::Source => "source",
+        }
+    }
+}
+
+impl Diagram {
+    /// Indices of the nodes grouped by the defining rusk.toml, sorted by its path.
+    /// Files without a task are collected in the group of None.
+    fn groups(&self) -> Vec<(Option<&NormarizedPath>, Vec<usize>)> {
+        self.nodes
+            .iter()
+            .enumerate()
+            .map(|(i, node)| (node.config.as_ref(), i))
+            .into_group_map()
+            .into_iter()
+            .sorted_by_key(|(config, _)| config.map(|config| config.as_abs_str()))
+            .collect()
+    }
+    /// Display in Graphviz DOT
+    pub fn dot(&self) -> impl Display + '_ {
+        Dot(self)
+    }
+    /// Display in Mermaid flowchart
+    pub fn mermaid(&self) -> impl Display + '_ {
+        Mermaid(self)
+    }
+}
+
+/// Display of Diagram in Graphviz DOT
+struct Dot<'a>(&'a Diagram);
+
+impl Display for Dot<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        ////////////////////////////////////////////////
+        //
+        // Format:
+        //     digraph rusk {
+        //       subgraph cluster_(i) { label="(rusk.toml)"; n(i) [...]; }
+        //       n(i) -> n(j);
+        //     }
+        //
+        ////////////////////////////////////////////////
+
+        /// Escape the string in a double-quoted ID
+        fn quote(s: &str) -> String {
+            format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+        }
+
+        let Dot(diagram) = self;
+        writeln!(f, "digraph rusk {{")?;
+        writeln!(f, "  node [fontname=\"sans-serif\"];")?;
+        for (i, (config, nodes)) in diagram.groups().into_iter().enumerate() {
+            let indent = if let Some(config) = config {
+                writeln!(f, "  subgraph cluster_{i} {{")?;
+                writeln!(f, "    label={};", quote(config.as_short_str()))?;
+                "    "
+            } else {
+                "  "
+            };
+            for n in nodes {
+                let DiagramNode {
+                    key,
+                    kind,
+                    up_to_date,
+                    ..
+                } = &diagram.nodes[n];
+                let (shape, color) = match kind {
+                    NodeKind::Phony => ("box", "purple"),
+                    NodeKind::File => ("note", "blue"),
+                    NodeKind::Source => ("note", "gray"),
+                };
+                let style = match (kind, up_to_date) {
+                    (NodeKind::Source, true) => "dashed,filled",
+                    (NodeKind::Source, false) => "dashed",
+                    (_, true) => "filled",
+                    (_, false) => "solid",
+                };
+                write!(f, "{indent}n{n} [label={}, ", quote(key.as_ref()))?;
+                writeln!(
+                    f,
+                    "shape={shape}, color={color}, style=\"{style}\", fillcolor=\"#d4edda\"];"
+                )?;
+            }
+            if config.is_some() {
+                writeln!(f, "  }}")?;
+            }
+        }
+        for (from, to) in &diagram.edges {
+            writeln!(f, "  n{from} -> n{to};")?;
+        }
+        writeln!(f, "}}")
+    }
+}
+
+/// Display of Diagram in Mermaid flowchart
+struct Mermaid<'a>(&'a Diagram);
+
+impl Display for Mermaid<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        ////////////////////////////////////////////////
+        //
+        // Format:
+        //     flowchart TD
+        //       subgraph g(i) ["(rusk.toml)"]
+        //         n(i)["(task_name)"]
+        //       end
+        //       n(i) --> n(j)
+        //       class n(i),n(j) (class)
+        //
+        ////////////////////////////////////////////////
+
+        /// Escape the string in a double-quoted label
+        fn quote(s: &str) -> String {
+            format!("\"{}\"", s.replace('"', "#quot;"))
+        }
+
+        let Mermaid(diagram) = self;
+        writeln!(f, "flowchart TD")?;
+        for (i, (config, nodes)) in diagram.groups().into_iter().enumerate() {
+            let indent = if let Some(config) = config {
+                writeln!(f, "  subgraph g{i} [{}]", quote(config.as_short_str()))?;
+                "    "
+            } else {
+                "  "
+            };
+            for n in nodes {
+                writeln!(f, "{indent}n{n}[{}]", quote(diagram.nodes[n].key.as_ref()))?;
+            }
+            if config.is_some() {
+                writeln!(f, "  end")?;
+            }
+        }
+        for (from, to) in &diagram.edges {
+            writeln!(f, "  n{from} --> n{to}")?;
+        }
+        // NOTE: A class statement takes a single class, so the nodes are listed per class
+        let classes = diagram
+            .nodes
+            .iter()
+            .enumerate()
+            .flat_map(|(n, node)| {
+                let fresh = node.up_to_date.then_some("fresh");
+                [Some(node.kind.as_str()), fresh]
+                    .into_iter()
+                    .flatten()
+                    .map(move |class| (class, n))
+            })
+            .into_group_map();
+        let kinds = [NodeKind::Phony, NodeKind::File, NodeKind::Source].map(NodeKind::as_str);
+        for class in kinds.into_iter().chain(["fresh"]) {
+            if let Some(nodes) = classes.get(class) {
+                let nodes = nodes.iter().map(|n| format!("n{n}")).join(",");
+                writeln!(f, "  class {nodes} {class}")?;
+            }
+        }
+        writeln!(f, "  classDef phony stroke:purple")?;
+        writeln!(f, "  classDef file stroke:blue")?;
+        writeln!(f, "  classDef source stroke:gray,stroke-dasharray:4")?;
+        writeln!(f, "  classDef fresh fill:#d4edda")
+    }
+}
@@ -1,16 +1,18 @@
 use std::{
     borrow::Borrow,
+    fmt::Debug,
     hash::Hash,
-    marker::PhantomData,
     ops::{Deref, DerefMut},
     rc::Rc,
 };
 
 use hashbrown::{hash_map::EntryRef, HashMap, HashSet};
+use itertools::Itertools;
 
 /// Node of a tree
 pub struct TreeNode<K: Hash + Eq + Clone, T> {
-    _key: PhantomData<fn() -> K>,
+    /// Key of the node
+    pub key: K,
     /// Inner-Item of the node
     pub item: T,
     /// Children of the node
@@ -19,27 +21,27 @@ pub struct TreeNode<K: Hash + Eq + Clone, T> {
 
 /// Error of TreeNode
 #[derive(Debug, thiserror::Error)]
-pub enum TreeNodeCreationError<K: Hash + Eq + Clone> {
-    /// Item not found
-    #[error("Item named {0:?} not found")]
-    ItemNotFound(K),
-    /// Circular dependency found
-    #[error("Circular dependency found around {0:?}")]
-    CircularDependency(K),
+pub enum TreeNodeCreationError<K: Hash + Eq + Clone + Debug> {
+    /// Item not found, required by the parent or given as a target if the parent is None
+    #[error("Item named {key:?} not found")]
+    ItemNotFound { key: K, parent: Option<K> },
+    /// Circular dependencies found, each of which is the path starting and ending with the same key
+    #[error("Circular dependency found: {}", .0.iter().map(|cycle| cycle.iter().map(|key| format!("{key:?}")).join(" -> ")).join(", "))]
+    CircularDependency(Vec<Vec<K>>),
 }
 
-/// To manage parents of a node. When the manager is dropped, it removes the parent from the set.
-struct ParentsManager<'a, K: Hash + Eq + Clone>(&'a mut HashSet<K>, &'a K);
+/// To manage ancestors of a node in order. When the manager is dropped, it removes the parent from the path.
+struct ParentsManager<'a, K: Hash + Eq + Clone>(&'a mut Vec<K>);
 
 impl<'a, K: Hash + Eq + Clone> ParentsManager<'a, K> {
-    fn new(parents: &'a mut HashSet<K>, name: &'a K) -> Self {
-        parents.insert(name.clone());
-        Self(parents, name)
+    fn new(parents: &'a mut Vec<K>, name: &K) -> Self {
+        parents.push(name.clone());
+        Self(parents)
     }
 }
 
 impl<K: Hash + Eq + Clone> Deref for ParentsManager<'_, K> {
-    type Target = HashSet<K>;
+    type Target = Vec<K>;
     fn deref(&self) -> &Self::Target {
         self.0
     }
@@ -53,12 +55,13 @@ impl<K: Hash + Eq + Clone> DerefMut for ParentsManager<'_, K> {
 
 impl<K: Hash + Eq + Clone> Drop for ParentsManager<'_, K> {
     fn drop(&mut self) {
-        self.0.remove(self.1);
+        self.0.pop();
     }
 }
 
-impl<K: Hash + Eq + Clone, D: DigraphItem<K>> TreeNode<K, D> {
+impl<K: Hash + Eq + Clone + Debug, D: DigraphItem<K>> TreeNode<K, D> {
     /// Create trees from a directed graph.
+    /// All the cycles reachable from the targets are reported at once.
     pub fn new_vec(
         hashmap: HashMap<K, D>,
         targets: impl IntoIterator<Item: Borrow<K>>,
@@ -67,27 +70,36 @@ impl<K: Hash + Eq + Clone, D: DigraphItem<K>> TreeNode<K, D> {
             Raw(D),
             Node(Rc<TreeNode<K, D>>),
         }
-        fn convert<K: Hash + Eq + Clone, D: DigraphItem<K>>(
+        fn convert<K: Hash + Eq + Clone + Debug, D: DigraphItem<K>>(
             name: &K,
             raw: D,
             list: &mut HashMap<K, RawOrNode<K, D>>,
-            parents: &mut HashSet<K>,
+            parents: &mut Vec<K>,
+            cycles: &mut Vec<Vec<K>>,
         ) -> Result<TreeNode<K, D>, TreeNodeCreationError<K>> {
             let mut parents = ParentsManager::new(parents, name);
 
             let mut children = vec![];
             for dep_name in raw.children().iter() {
-                if parents.contains(dep_name) {
-                    return Err(TreeNodeCreationError::CircularDependency(dep_name.clone()));
+                if let Some(start) = parents.iter().position(|parent| parent == dep_name) {
+                    // Skip the edge closing the cycle to look for the other cycles
+                    let mut cycle = parents[start..].to_vec();
+                    cycle.push(dep_name.clone());
+                    cycles.push(cycle);
+                    continue;
                 }
 
                 match list.entry_ref(dep_name) {
                     EntryRef::Vacant(_) => {
-                        return Err(TreeNodeCreationError::ItemNotFound(dep_name.clone()));
+                        return Err(TreeNodeCreationError::ItemNotFound {
+                            key: dep_name.clone(),
+                            parent: Some(name.clone()),
+                        });
                     }
                     EntryRef::Occupied(occupied) => match occupied.remove() {
                         RawOrNode::Raw(dep_item) => {
-                            let node = Rc::new(convert(dep_name, dep_item, list, &mut parents)?);
+                            let node =
+                                Rc::new(convert(dep_name, dep_item, list, &mut parents, cycles)?);
                             list.insert(dep_name.clone(), RawOrNode::Node(node.clone()));
                             children.push(node);
                         }
@@ -99,13 +111,14 @@ impl<K: Hash + Eq + Clone, D: DigraphItem<K>> TreeNode<K, D> {
                 }
             }
             Ok(TreeNode::<K, D> {
-                _key: PhantomData,
+                key: name.clone(),
                 item: raw,
                 children,
             })
         }
 
         let mut roots = vec![];
+        let mut cycles = vec![];
         let mut hashmap = hashmap
             .into_iter()
             .map(|(k, v)| (k, RawOrNode::Raw(v)))
@@ -113,17 +126,55 @@ impl<K: Hash + Eq + Clone, D: DigraphItem<K>> TreeNode<K, D> {
         for label in targets {
             let label = label.borrow();
             let Some(item) = hashmap.remove(label) else {
-                return Err(TreeNodeCreationError::ItemNotFound(label.clone()));
+                return Err(TreeNodeCreationError::ItemNotFound {
+                    key: label.clone(),
+                    parent: None,
+                });
             };
-            if let RawOrNode::Raw(raw) = item {
-                let node = convert(label, raw, &mut hashmap, &mut HashSet::new())?;
-                roots.push(node);
+            match item {
+                RawOrNode::Raw(raw) => {
+                    let node = convert(label, raw, &mut hashmap, &mut Vec::new(), &mut cycles)?;
+                    roots.push(node);
+                }
+                // Already a dependency of another target, which may be shared by later targets
+                node @ RawOrNode::Node(_) => {
+                    hashmap.insert(label.clone(), node);
+                }
             }
         }
+        if !cycles.is_empty() {
+            return Err(TreeNodeCreationError::CircularDependency(cycles));
+        }
         Ok(roots)
     }
 }
 
+impl<K: Hash + Eq + Clone, T> TreeNode<K, T> {
+    /// Collect the nodes of the trees in post-order (children first), visiting each key once.
+    pub fn post_order<'a>(roots: impl IntoIterator<Item = &'a Self>) -> Vec<&'a Self> {
+        fn visit<'a, K: Hash + Eq + Clone, T>(
+            node: &'a TreeNode<K, T>,
+            visited: &mut HashSet<&'a K>,
+            order: &mut Vec<&'a TreeNode<K, T>>,
+        ) {
+            if !visited.insert(&node.key) {
+                return;
+            }
+            for child in node.children.iter() {
+                visit(child, visited, order);
+            }
+            order.push(node);
+        }
+
+        let mut order = Vec::new();
+        let mut visited = HashSet::new();
+        for root in roots {
+            visit(root, &mut visited, &mut order);
+        }
+        order
+    }
+}
+
 /// Vertex of a directed graph
 pub trait DigraphItem<K: Hash + Eq + Clone> {
     /// Get children of the vertex
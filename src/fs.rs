@@ -1,17 +1,23 @@
-use std::{borrow::Cow, ffi::OsStr, fmt::Display, path::Path};
+use std::{borrow::Cow, ffi::OsStr, fmt::Display, num::NonZeroUsize, path::Path, time::Duration};
 
-use anyhow::Error;
 use colored::Colorize;
 use futures::future::join_all;
-use hashbrown::{hash_map::EntryRef, HashMap};
-use ignore::{WalkBuilder, WalkState};
+use hashbrown::{hash_map::EntryRef, HashMap, HashSet};
+use ignore::{overrides::OverrideBuilder, WalkBuilder, WalkState};
 use itertools::Itertools;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use schemars::{generate::SchemaSettings, transform::RecursiveTransform, Schema};
+use serde_json::Value;
 use toml::Table;
+use toml_edit::{Item, TableLike};
 use unicode_width::UnicodeWidthStr;
 
 use crate::{
-    path::NormarizedPath,
-    rusk::Task,
+    diagnostic::{ConfigSource, Diagnostic, Step, TaskOrigin, ARGS, DEPENDS, INPUTS},
+    path::{repository_root, NormarizedPath},
+    rusk::{is_valid_arg_name, PatternRule, Settings, Task, TaskArg, UnknownFields, VerifyOutputs},
+    service::{ReadyProbe, ServiceConfig, DEFAULT_READY_TIMEOUT},
     taskkey::{TaskKey, TaskKeyRef, TaskKeyRelative},
 };
 
@@ -51,17 +57,23 @@ impl Display for TasksListItemPretty<'_> {
         ////////////////////////////////////////////////
         //
         // Format:
-        //     (task_name)  (description)"  in "(path)
+        //     (task_name) (args)  (description)"  in "(path)
         //
         ////////////////////////////////////////////////
 
         let width = self.task_word_width + 2;
         match content {
-            Ok(TaskListItemContent { key, description }) => {
-                // (task_name)
+            Ok(content) => {
+                let TaskListItemContent {
+                    key, description, ..
+                } = content;
+                // (task_name) (args)
                 let task_key = key.as_task_key();
                 write!(f, "{}", task_key)?;
-                for _ in 0..width - task_key.as_ref().width() {
+                for arg in content.args_display() {
+                    write!(f, " {}", arg.cyan())?;
+                }
+                for _ in 0..width - content.width() {
                     ' '.fmt(f)?;
                 }
                 if let Some(description) = description {
@@ -144,6 +156,28 @@ struct TaskListItemContent<'a> {
     key: TaskKeyRef<'a>,
     /// Task description
     description: Option<&'a str>,
+    /// Arguments which the task accepts
+    args: &'a [TaskArgDeserializer],
+}
+
+impl TaskListItemContent<'_> {
+    /// Arguments formatted as `<required>` or `[optional=default]`
+    fn args_display(&self) -> impl Iterator<Item = String> + '_ {
+        self.args
+            .iter()
+            .map(|arg| match (&arg.default, arg.required) {
+                (Some(default), _) => format!("[{}={}]", arg.name, default),
+                (None, true) => format!("<{}>", arg.name),
+                (None, false) => format!("[{}]", arg.name),
+            })
+    }
+    /// Display width of the task name and its arguments
+    fn width(&self) -> usize {
+        self.args_display()
+            .map(|arg| arg.width() + 1)
+            .chain([self.key.as_task_key().as_ref().width()])
+            .sum()
+    }
 }
 
 impl Ord for TaskListItemContent<'_> {
@@ -170,7 +204,9 @@ impl Display for TasksListItem<'_> {
         }
 
         match &self.content {
-            Ok(TaskListItemContent { key, description }) => {
+            Ok(TaskListItemContent {
+                key, description, ..
+            }) => {
                 // (task_name)
                 writet!(key);
                 if let Some(description) = description {
@@ -208,6 +244,7 @@ impl RuskfileComposer {
                     content: Ok(TaskListItemContent {
                         key: key.as_task_key(Path::parent(path).unwrap()),
                         description: task.description.as_deref(),
+                        args: &task.args,
                     }),
                     path,
                 })),
@@ -222,7 +259,7 @@ impl RuskfileComposer {
             .iter()
             .map(|a| {
                 if let Ok(content) = &a.content {
-                    content.key.as_task_key().as_ref().width()
+                    content.width()
                 } else {
                     0
                 }
@@ -245,6 +282,61 @@ impl RuskfileComposer {
         })
     }
 
+    /// List all tasks and errors in the machine-readable form, sorted
+    pub fn tasks_info(&self) -> (Vec<TaskInfo<'_>>, Vec<LoadError<'_>>) {
+        let tasks = self
+            .map
+            .iter()
+            .filter_map(|(path, res)| Some((path, res.as_ref().ok()?)))
+            .flat_map(|(path, config)| {
+                let base = Path::parent(path).unwrap(); // NOTE: path is a file, so it has a parent directory
+                config.tasks.iter().map(move |(key, task)| {
+                    // Broken fields are shown as empty, because they are reported when the task is executed
+                    let inner: TaskDeserializerInner =
+                        task.inner.clone().try_into().unwrap_or_default();
+                    let key = key.as_task_key(base).into_task_key();
+                    TaskInfo {
+                        kind: match key {
+                            TaskKey::Phony(_) => "phony",
+                            TaskKey::File(_) => "file",
+                        },
+                        key: key.as_ref().to_owned(),
+                        description: task.description.as_deref(),
+                        config: path.as_abs_str(),
+                        cwd: NormarizedPath::from(base.join(inner.cwd.as_ref()))
+                            .as_abs_str()
+                            .to_owned(),
+                        depends: inner
+                            .depends
+                            .into_iter()
+                            .map(|dep| dep.into_task_key(base).as_ref().to_owned())
+                            .collect(),
+                        envs: inner.envs.into_keys().sorted().collect(),
+                        args: &task.args,
+                    }
+                })
+            })
+            .sorted_by(|a, b| (&a.key, a.config).cmp(&(&b.key, b.config)))
+            .collect();
+        let errors = self
+            .map
+            .iter()
+            .filter_map(|(path, res)| {
+                Some(LoadError {
+                    config: path.as_abs_str(),
+                    error: res.as_ref().err()?,
+                })
+            })
+            .sorted_by_key(|err| err.config)
+            .collect();
+        (tasks, errors)
+    }
+
+    /// Paths of the loaded rusk.toml files
+    pub fn paths(&self) -> impl Iterator<Item = &NormarizedPath> {
+        self.map.keys()
+    }
+
     /// Walk through the directory and find all rusk.toml files
     pub async fn walkdir(&mut self, path: impl AsRef<Path>) {
         let threads = {
@@ -262,21 +354,7 @@ impl RuskfileComposer {
                                     if let Some(ft) = entry.file_type() {
                                         if ft.is_file() && is_ruskfile(entry.file_name()) {
                                             let path = NormarizedPath::from(entry.path());
-                                            tx.blocking_send(async move {
-                                                // make Future of Config
-                                                let res = tokio::fs::read_to_string(&path)
-                                                    .await
-                                                    .map_err(Error::from)
-                                                    .and_then(|content| {
-                                                        toml::from_str::<RuskfileDeserializer>(
-                                                            &content,
-                                                        )
-                                                        .map_err(Error::from)
-                                                    })
-                                                    .map_err(|err| err.to_string());
-                                                (path, res)
-                                            })
-                                            .unwrap();
+                                            tx.blocking_send(load_ruskfile(path)).unwrap();
                                         }
                                         return WalkState::Continue;
                                     }
@@ -294,89 +372,856 @@ impl RuskfileComposer {
         };
         self.map.extend(join_all(threads).await);
     }
+
+    /// Global settings. Each setting is taken from the shallowest rusk.toml which defines it
+    pub fn settings(&self) -> Settings {
+        self.map
+            .iter()
+            .filter_map(|(path, res)| Some((path.components().count(), res.as_ref().ok()?)))
+            .sorted_by_key(|(depth, _)| *depth)
+            .fold(Settings::default(), |settings, (_, config)| Settings {
+                jobs: settings.jobs.or(config.settings.jobs),
+                fingerprint: settings.fingerprint.or(config.settings.fingerprint),
+                verify_outputs: settings.verify_outputs.or(config.settings.verify_outputs),
+                delete_on_error: settings.delete_on_error.or(config.settings.delete_on_error),
+                unknown_fields: settings.unknown_fields.or(config.settings.unknown_fields),
+            })
+    }
+
+    /// Problems of the loaded rusk.toml files against the format this rusk understands, sorted by path
+    pub fn schema_errors(&self) -> Vec<Diagnostic<RuskfileDeserializeError>> {
+        self.map
+            .iter()
+            .filter_map(|(path, res)| Some((path, res.as_ref().ok()?)))
+            .sorted_by(|(a, _), (b, _)| a.as_abs_str().cmp(b.as_abs_str()))
+            .flat_map(|(path, config)| {
+                let source = ConfigSource::new(path.clone(), config.text.clone());
+                let version = check_version(&source, config.version).err();
+                version.into_iter().chain(check_fields(&source))
+            })
+            .collect()
+    }
+
+    /// Load the given rusk.toml files instead of walking through the directory
+    pub async fn load(&mut self, paths: impl IntoIterator<Item = NormarizedPath>) {
+        self.map
+            .extend(join_all(paths.into_iter().map(load_ruskfile)).await);
+    }
+}
+
+/// Task in the machine-readable list. The fields are part of the stable schema documented in README.
+#[derive(serde::Serialize)]
+pub struct TaskInfo<'a> {
+    /// Name of the phony task, or the path of the file task relative to the current directory
+    key: String,
+    /// "phony" or "file"
+    kind: &'static str,
+    /// Description for help
+    description: Option<&'a str>,
+    /// Absolute path of the rusk.toml defining the task
+    config: &'a str,
+    /// Absolute path of the working directory
+    cwd: String,
+    /// Dependencies as declared, with the file paths relative to the current directory
+    depends: Vec<String>,
+    /// Names of the environment variables specific to the task, sorted
+    envs: Vec<String>,
+    /// Arguments which the task accepts
+    args: &'a [TaskArgDeserializer],
+}
+
+/// rusk.toml which failed to load, in the machine-readable list
+#[derive(serde::Serialize)]
+pub struct LoadError<'a> {
+    /// Absolute path of the rusk.toml
+    config: &'a str,
+    /// Error message
+    error: &'a str,
+}
+
+/// Read and deserialize a rusk.toml file
+async fn load_ruskfile(
+    path: NormarizedPath,
+) -> (NormarizedPath, Result<RuskfileDeserializer, String>) {
+    let res = match tokio::fs::read_to_string(&path).await {
+        Ok(content) => match toml::from_str::<RuskfileDeserializer>(&content) {
+            Ok(config) => Ok(RuskfileDeserializer {
+                text: content,
+                ..config
+            }),
+            Err(err) => {
+                let source = ConfigSource::new(path.clone(), content);
+                let span = err.span().map(|range| source.span(range));
+                Err(Diagnostic::new(err.message(), span).to_string())
+            }
+        },
+        Err(err) => Err(err.to_string()),
+    };
+    (path, res)
 }
 
 #[derive(Debug, thiserror::Error)]
 pub enum RuskfileDeserializeError {
     #[error("Task {0} is duplicated")]
     DuplicatedTaskName(TaskKey),
+    #[error("Task {task} has an invalid argument name {name:?}: only /^[a-zA-Z_][a-zA-Z0-9_]*$/ is allowed")]
+    InvalidArgumentName { task: TaskKey, name: String },
+    #[error("Task {task} has a duplicated argument {name:?}")]
+    DuplicatedArgument { task: TaskKey, name: String },
+    #[error("Task {task} has an invalid glob pattern {pattern:?}: {error}")]
+    InvalidGlob {
+        task: TaskKey,
+        pattern: String,
+        error: Box<ignore::Error>,
+    },
+    #[error("Task {task} has an invalid ready pattern: {error}")]
+    InvalidReadyPattern {
+        task: TaskKey,
+        error: Box<regex::Error>,
+    },
+    #[error("Task {task} is not a valid service: {reason}")]
+    InvalidService { task: TaskKey, reason: &'static str },
+    #[error("Unknown field {field:?} in {place}")]
+    UnknownField { field: String, place: String },
+    #[error(
+        "Unsupported rusk.toml version {0}: this rusk supports up to version {CONFIG_VERSION}"
+    )]
+    UnsupportedVersion(u32),
     #[error("Failed to convert Task: {0}")]
     DeserializeError(#[from] toml::de::Error),
 }
 
-impl TryFrom<RuskfileComposer> for HashMap<TaskKey, Task> {
-    type Error = RuskfileDeserializeError;
-    fn try_from(composer: RuskfileComposer) -> Result<Self, Self::Error> {
-        let RuskfileComposer { map } = composer;
-        let mut tasks = HashMap::new();
+/// Tasks and pattern rules defined in the config files, with the problems found in them
+pub struct Composed {
+    /// Tasks
+    pub tasks: HashMap<TaskKey, Task>,
+    /// Pattern rules
+    pub rules: Vec<PatternRule>,
+    /// Tasks which failed to convert, and conflicts between the tasks. The tasks involved are left out.
+    pub errors: Vec<Diagnostic<RuskfileDeserializeError>>,
+    /// Fields unknown to this rusk
+    pub unknown_fields: Vec<Diagnostic<RuskfileDeserializeError>>,
+}
+
+impl RuskfileComposer {
+    /// Convert the loaded config files into tasks, collecting all the problems instead of stopping at the first
+    pub fn compose(self) -> Composed {
+        let RuskfileComposer { map } = self;
+        let mut tasks: HashMap<TaskKey, Task> = HashMap::new();
+        let mut rule_keys = Vec::new();
+        let mut errors = Vec::new();
+        let mut unknown_fields = Vec::new();
+        // Sorted, so that the same one of the duplicated tasks is reported each time
+        let map = map
+            .into_iter()
+            .sorted_by(|(a, _), (b, _)| a.as_abs_str().cmp(b.as_abs_str()));
         for (path, res) in map {
             let Ok(config) = res else {
                 continue;
             };
-            let configfile_dir = path.into_parent().unwrap(); // NOTE: path is guaranteed to be a NormalizedPath of an existing file, so it should have a parent directory
-            for (key, TaskDeserializer { inner, .. }) in config.tasks {
-                let key = key.into_task_key(&configfile_dir);
-                let TaskDeserializerInner {
-                    envs,
-                    script,
-                    depends,
-                    cwd,
-                } = inner.try_into()?; // NOTE: It is guaranteed to be a table, and fields that are not present will have default values.
+            let configfile_dir = path.clone().into_parent().unwrap(); // NOTE: path is guaranteed to be a NormalizedPath of an existing file, so it should have a parent directory
+            let source = ConfigSource::new(path, config.text);
+            if let Err(err) = check_version(&source, config.version) {
+                errors.push(err);
+                continue;
+            }
+            unknown_fields.extend(check_fields(&source));
+            let config_tasks = config
+                .tasks
+                .into_iter()
+                .sorted_by(|(a, _), (b, _)| a.as_ref().cmp(b.as_ref()));
+            for (key, task) in config_tasks {
+                let (key, task, templates) = match load_task(&configfile_dir, &source, key, task) {
+                    Ok(loaded) => loaded,
+                    Err(err) => {
+                        errors.push(err);
+                        continue;
+                    }
+                };
                 match tasks.entry_ref(&key) {
-                    EntryRef::Occupied(_) => {
-                        return Err(RuskfileDeserializeError::DuplicatedTaskName(key));
+                    EntryRef::Occupied(e) => {
+                        let hint = format!("also defined in {}", e.get().origin.config());
+                        let span = task.origin.locate(&[]);
+                        let err = RuskfileDeserializeError::DuplicatedTaskName(key);
+                        errors.push(Diagnostic::new(err, span).with_hint(hint));
                     }
                     EntryRef::Vacant(e) => {
-                        e.insert(Task {
-                            envs,
-                            script,
-                            cwd: configfile_dir.join(cwd.as_ref()).into(),
-                            depends: depends
-                                .into_iter()
-                                .map(|key| key.into_task_key(&configfile_dir))
-                                .collect(),
-                        });
+                        if let Some(templates) = templates {
+                            rule_keys.push((key.clone(), templates));
+                        }
+                        e.insert(task);
                     }
                 }
             }
         }
-        Ok(tasks)
+        let rules = rule_keys
+            .into_iter()
+            .map(|(key, templates)| {
+                let task = tasks.remove(&key).unwrap(); // Inserted above
+                let TaskKey::File(target) = key else {
+                    unreachable!("only file tasks can be pattern rules")
+                };
+                PatternRule::new(&target, task, templates)
+            })
+            .collect();
+        // Each output must be produced by only one task
+        let mut outputs = HashSet::new();
+        for (key, task) in tasks
+            .iter()
+            .sorted_by(|(a, _), (b, _)| a.as_ref().cmp(b.as_ref()))
+        {
+            for (i, output) in task.outputs.iter().enumerate() {
+                let output = TaskKey::File(output.clone());
+                if tasks.contains_key(&output) || !outputs.insert(output.clone()) {
+                    let span = task.origin.locate(&[Step::Key("outputs"), Step::Index(i)]);
+                    let err = RuskfileDeserializeError::DuplicatedTaskName(output);
+                    let hint = format!("listed in the outputs of {key}");
+                    errors.push(Diagnostic::new(err, span).with_hint(hint));
+                }
+            }
+        }
+        Composed {
+            tasks,
+            rules,
+            errors,
+            unknown_fields,
+        }
+    }
+}
+
+/// Tasks and pattern rules defined in the config files
+impl TryFrom<RuskfileComposer> for (HashMap<TaskKey, Task>, Vec<PatternRule>) {
+    type Error = Diagnostic<RuskfileDeserializeError>;
+    fn try_from(composer: RuskfileComposer) -> Result<Self, Self::Error> {
+        let mode = composer.settings().unknown_fields.unwrap_or_default();
+        let Composed {
+            tasks,
+            rules,
+            errors,
+            unknown_fields,
+        } = composer.compose();
+        if let Some(err) = errors.into_iter().next() {
+            return Err(err);
+        }
+        for err in unknown_fields {
+            match mode {
+                UnknownFields::Error => return Err(err),
+                UnknownFields::Warn => {
+                    eprintln!("{}: {err}", "warning".on_yellow().black().bold());
+                }
+            }
+        }
+        Ok((tasks, rules))
+    }
+}
+
+/// Task loaded from rusk.toml, with the paths written with `%` if it is a pattern rule
+type LoadedTask = (TaskKey, Task, Option<HashSet<NormarizedPath>>);
+
+/// Convert a task in the config file, whose file paths are relative to the directory
+fn load_task(
+    configfile_dir: &Path,
+    source: &ConfigSource,
+    key: TaskKeyRelative,
+    TaskDeserializer { inner, args, .. }: TaskDeserializer,
+) -> Result<LoadedTask, Diagnostic<RuskfileDeserializeError>> {
+    let is_rule = is_pattern(&key);
+    let origin = TaskOrigin::new(source.clone(), &key);
+    let key = key.into_task_key(configfile_dir);
+    for (i, arg) in args.iter().enumerate() {
+        let span = || origin.locate(&[ARGS, Step::Index(i), Step::Key("name")]);
+        if !is_valid_arg_name(&arg.name) {
+            let err = RuskfileDeserializeError::InvalidArgumentName {
+                task: key,
+                name: arg.name.clone(),
+            };
+            return Err(Diagnostic::new(err, span()));
+        }
+        if args[..i].iter().any(|other| other.name == arg.name) {
+            let err = RuskfileDeserializeError::DuplicatedArgument {
+                task: key,
+                name: arg.name.clone(),
+            };
+            return Err(Diagnostic::new(err, span()));
+        }
+    }
+    let TaskDeserializerInner {
+        envs,
+        script,
+        depends,
+        inputs,
+        outputs,
+        cwd,
+        fingerprint,
+        verify_outputs,
+        delete_on_error,
+        service,
+        ready,
+        ready_timeout,
+    } = inner // NOTE: It is guaranteed to be a table, and fields that are not present will have default values.
+        .try_into()
+        .map_err(|err: toml::de::Error| Diagnostic::new(err.into(), origin.locate(&[])))?;
+    // Paths written with `%` in a pattern rule, to be substituted with the stem.
+    // NOTE: Told apart before resolved, since the directory of the config file may contain `%`.
+    let mut templates = HashSet::new();
+    let mut template = |path: &str, resolved: &NormarizedPath| {
+        if is_rule && path.contains('%') {
+            templates.insert(resolved.clone());
+        }
+    };
+    let mut deps = Vec::new();
+    for (i, dep) in depends.into_iter().enumerate() {
+        match &dep {
+            TaskKeyRelative::File(path) => deps.extend(
+                expand_path(configfile_dir, path.as_ref(), &key, is_rule)
+                    .map_err(|err| {
+                        let span = origin.locate(&[DEPENDS, Step::Index(i)]);
+                        Diagnostic::new(err, span)
+                    })?
+                    .into_iter()
+                    .inspect(|file| template(path.as_ref(), file))
+                    .map(TaskKey::File),
+            ),
+            TaskKeyRelative::Phony(_) => deps.push(dep.into_task_key(configfile_dir)),
+        }
+    }
+    let inputs = inputs
+        .map(|inputs| {
+            let mut files = Vec::new();
+            for (i, input) in inputs.iter().enumerate() {
+                let expanded =
+                    expand_path(configfile_dir, input, &key, is_rule).map_err(|err| {
+                        let span = origin.locate(&[INPUTS, Step::Index(i)]);
+                        Diagnostic::new(err, span)
+                    })?;
+                expanded.iter().for_each(|file| template(input, file));
+                files.extend(expanded);
+            }
+            Ok::<_, Diagnostic<_>>(files)
+        })
+        .transpose()?;
+    let cwd: NormarizedPath = configfile_dir.join(cwd.as_ref()).into();
+    let service = if service {
+        let reason = match &key {
+            TaskKey::File(_) => Some(("file tasks cannot be services", "service")),
+            TaskKey::Phony(_) if !outputs.is_empty() => {
+                Some(("services cannot have outputs", "outputs"))
+            }
+            TaskKey::Phony(_) => None,
+        };
+        if let Some((reason, field)) = reason {
+            let span = origin.locate(&[Step::Key(field)]);
+            let err = RuskfileDeserializeError::InvalidService { task: key, reason };
+            return Err(Diagnostic::new(err, span));
+        }
+        let ready = match ready {
+            None => None,
+            Some(ReadyDeserializer::Port(port)) => Some(ReadyProbe::Port(port)),
+            Some(ReadyDeserializer::Log(pattern)) => match Regex::new(&pattern) {
+                Ok(pattern) => Some(ReadyProbe::Log(pattern)),
+                Err(error) => {
+                    let span = origin.locate(&[Step::Key("ready"), Step::Key("log")]);
+                    let err = RuskfileDeserializeError::InvalidReadyPattern {
+                        task: key,
+                        error: Box::new(error),
+                    };
+                    return Err(Diagnostic::new(err, span));
+                }
+            },
+            Some(ReadyDeserializer::File(file)) => Some(ReadyProbe::File(cwd.join(file).into())),
+        };
+        Some(ServiceConfig {
+            ready,
+            timeout: ready_timeout.map_or(DEFAULT_READY_TIMEOUT, Duration::from_secs),
+        })
+    } else {
+        if ready.is_some() || ready_timeout.is_some() {
+            let span = origin.locate(&[Step::Keys(&["ready", "ready_timeout"])]);
+            let err = RuskfileDeserializeError::InvalidService {
+                task: key,
+                reason: "ready and ready_timeout require service = true",
+            };
+            return Err(Diagnostic::new(err, span));
+        }
+        None
+    };
+    let task = Task {
+        origin,
+        envs,
+        script,
+        cwd,
+        depends: deps.into_iter().unique().collect(),
+        inputs,
+        outputs: outputs
+            .iter()
+            .map(|output| {
+                let resolved = configfile_dir.join(output).into();
+                template(output, &resolved);
+                resolved
+            })
+            .collect(),
+        args: args
+            .into_iter()
+            .map(
+                |TaskArgDeserializer {
+                     name,
+                     default,
+                     required,
+                     ..
+                 }| TaskArg {
+                    name,
+                    default,
+                    required,
+                },
+            )
+            .collect(),
+        fingerprint,
+        verify_outputs,
+        delete_on_error,
+        service,
+    };
+    Ok((key, task, is_rule.then_some(templates)))
+}
+
+/// Check if the task key is a pattern containing `%`
+fn is_pattern(key: &TaskKeyRelative) -> bool {
+    matches!(key, TaskKeyRelative::File(path) if path.as_ref().contains('%'))
+}
+
+/// Files tracked by git among the given files.
+/// Files outside any git repository, or all the files if git is not available, are regarded as untracked.
+pub fn git_tracked<'a>(
+    files: impl IntoIterator<Item = &'a NormarizedPath>,
+) -> HashSet<NormarizedPath> {
+    // NOTE: git fails if any path is outside the repository, so the files are asked per directory.
+    let by_dir = files
+        .into_iter()
+        .filter_map(|file| Some((file.parent()?, file.file_name()?)))
+        .into_group_map();
+    let mut tracked = HashSet::new();
+    for (dir, names) in by_dir {
+        let Ok(output) = std::process::Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(["ls-files", "-z", "--"])
+            .args(names)
+            .stderr(std::process::Stdio::null())
+            .output()
+        else {
+            break; // git is not available
+        };
+        if !output.status.success() {
+            continue;
+        }
+        tracked.extend(
+            output
+                .stdout
+                .split(|&b| b == 0)
+                .filter(|name| !name.is_empty())
+                .map(|name| NormarizedPath::from(dir.join(String::from_utf8_lossy(name).as_ref()))),
+        );
+    }
+    tracked
+}
+
+/// Latest version of the rusk.toml format which this rusk understands
+const CONFIG_VERSION: u32 = 1;
+
+/// JSON Schema of rusk.toml, generated from the deserializers
+pub fn json_schema() -> Schema {
+    SchemaSettings::draft07()
+        .with_transform(RecursiveTransform(remove_null))
+        .into_generator()
+        .into_root_schema_for::<RuskfileDeserializer>()
+}
+
+/// Adapt the schema to TOML, which has no null. Optional fields are just omitted instead.
+fn remove_null(schema: &mut Schema) {
+    let Some(object) = schema.as_object_mut() else {
+        return;
+    };
+    if object.get("default") == Some(&Value::Null) {
+        object.remove("default");
+    }
+    if let Some(Value::Array(types)) = object.get_mut("type") {
+        types.retain(|ty| ty != "null");
+        if let [ty] = types.as_slice() {
+            let ty = ty.clone();
+            object.insert("type".to_owned(), ty);
+        }
+    }
+    if let Some(Value::Array(variants)) = object.get_mut("anyOf") {
+        variants.retain(|variant| variant.get("type") != Some(&Value::from("null")));
+        if variants.len() == 1 {
+            // Wrapped in allOf, since the other keywords beside $ref are ignored in draft 7
+            let variants = object.remove("anyOf").unwrap(); // Checked above
+            object.insert("allOf".to_owned(), variants);
+        }
+    }
+}
+
+/// Fields of each table in rusk.toml
+struct KnownFields {
+    /// Top-level fields
+    root: Vec<String>,
+    /// Fields of the global settings
+    settings: Vec<String>,
+    /// Fields of a task, including the aliases
+    task: Vec<String>,
+    /// Fields of an argument of a task
+    arg: Vec<String>,
+}
+
+/// Known fields taken from the JSON Schema, so that they follow the deserializers
+static KNOWN_FIELDS: Lazy<KnownFields> = Lazy::new(|| {
+    let schema = json_schema();
+    let fields = |schema: Option<&Value>| {
+        schema
+            .and_then(|schema| schema.get("properties")?.as_object())
+            .map(|properties| properties.keys().cloned().collect())
+            .unwrap_or_default()
+    };
+    let definition = |name: &str| schema.get("definitions").and_then(|defs| defs.get(name));
+    KnownFields {
+        root: fields(Some(schema.as_value())),
+        settings: fields(definition("SettingsDeserializer")),
+        task: fields(definition("TaskDeserializer")),
+        arg: fields(definition("TaskArgDeserializer")),
+    }
+});
+
+/// Aliases of the task fields with the fields they stand for
+const TASK_ALIASES: &[(&str, &str)] = &[("params", "args"), ("sources", "inputs")];
+
+/// Add the aliases to the schema of a task, since serde aliases are not reflected in it
+fn add_aliases(schema: &mut Schema) {
+    let Some(properties) = schema.get_mut("properties").and_then(Value::as_object_mut) else {
+        return;
+    };
+    for (alias, field) in TASK_ALIASES {
+        if let Some(property) = properties.get(*field).cloned() {
+            properties.insert(alias.to_string(), property);
+        }
+    }
+}
+
+/// Check if the version marker of the rusk.toml is supported.
+/// Files without the marker are regarded as the latest version.
+fn check_version(
+    source: &ConfigSource,
+    version: Option<u32>,
+) -> Result<(), Diagnostic<RuskfileDeserializeError>> {
+    match version {
+        Some(version) if version == 0 || version > CONFIG_VERSION => {
+            let span = source.locate(&[Step::Key("version")]);
+            let err = Diagnostic::new(RuskfileDeserializeError::UnsupportedVersion(version), span);
+            Err(if version > CONFIG_VERSION {
+                err.with_hint("upgrade rusk to load this file")
+            } else {
+                err
+            })
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Find the fields unknown to this rusk in the order of the file, suggesting the similar known ones
+fn check_fields(source: &ConfigSource) -> Vec<Diagnostic<RuskfileDeserializeError>> {
+    /// Report the keys of the table which are not known
+    fn check(
+        source: &ConfigSource,
+        table: &dyn TableLike,
+        known: &[String],
+        place: &str,
+        found: &mut Vec<Diagnostic<RuskfileDeserializeError>>,
+    ) {
+        for (field, _) in table
+            .iter()
+            .filter(|(field, _)| !known.iter().any(|known| known == field))
+        {
+            let span = table
+                .get_key_value(field)
+                .and_then(|(key, _)| key.span())
+                .map(|range| source.span(range));
+            let err = RuskfileDeserializeError::UnknownField {
+                field: field.to_owned(),
+                place: place.to_owned(),
+            };
+            let err = Diagnostic::new(err, span);
+            found.push(match similar_field(field, known) {
+                Some(similar) => err.with_hint(format!("did you mean `{similar}`?")),
+                None => err,
+            });
+        }
+    }
+
+    let mut found = Vec::new();
+    let Some(doc) = source.document() else {
+        return found;
+    };
+    let root = doc.as_table();
+    let known = &*KNOWN_FIELDS;
+    check(source, root, &known.root, "the top level", &mut found);
+    if let Some(settings) = root.get("settings").and_then(Item::as_table_like) {
+        check(source, settings, &known.settings, "[settings]", &mut found);
+    }
+    let tasks = root.get("tasks").and_then(Item::as_table_like);
+    for (name, task) in tasks.into_iter().flat_map(TableLike::iter) {
+        let Some(task) = task.as_table_like() else {
+            continue;
+        };
+        let place = format!("task {name}");
+        check(source, task, &known.task, &place, &mut found);
+        // Arguments are written either in an inline array or in an array of tables
+        for args in ["args", "params"]
+            .into_iter()
+            .filter_map(|field| task.get(field))
+        {
+            let inline = args
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|arg| arg.as_inline_table().map(|arg| arg as &dyn TableLike));
+            let tables = args
+                .as_array_of_tables()
+                .into_iter()
+                .flatten()
+                .map(|arg| arg as &dyn TableLike);
+            for arg in inline.chain(tables) {
+                let place = format!("an argument of task {name}");
+                check(source, arg, &known.arg, &place, &mut found);
+            }
+        }
+    }
+    found
+}
+
+/// Known field most similar to the unknown one, if similar enough to be a typo.
+/// Otherwise the only field starting with the same three characters, as in `enviroment` for `envs`.
+fn similar_field<'a>(field: &str, known: &'a [String]) -> Option<&'a str> {
+    let similar = known
+        .iter()
+        .map(|candidate| (strsim::jaro_winkler(field, candidate), candidate.as_str()))
+        .filter(|(similarity, _)| *similarity >= 0.75)
+        .max_by(|(a, _), (b, _)| a.total_cmp(b))
+        .map(|(_, candidate)| candidate);
+    similar.or_else(|| {
+        let prefix = field.get(..3)?;
+        known
+            .iter()
+            .filter(|candidate| candidate.starts_with(prefix))
+            .exactly_one()
+            .ok()
+            .map(String::as_str)
+    })
+}
+
+/// Check if the dependency is a glob pattern
+pub fn is_glob(pattern: &str) -> bool {
+    pattern.contains(['*', '?', '[', '{'])
+}
+
+/// Resolve the file path of the task relative to the base directory, expanding the glob pattern.
+/// Paths of a pattern rule containing `%` are kept as they are, to be expanded when instantiated.
+/// A pattern is also kept as a literal path if the file of that name exists, or if nothing matches,
+/// so that it is reported as a missing file instead of being dropped.
+fn expand_path(
+    base: &Path,
+    path: &str,
+    task: &TaskKey,
+    is_rule: bool,
+) -> Result<Vec<NormarizedPath>, RuskfileDeserializeError> {
+    let literal = base.join(path);
+    if !is_glob(path) || (is_rule && path.contains('%')) || literal.exists() {
+        return Ok(vec![literal.into()]);
+    }
+    let files = expand_glob(base, path).map_err(|error| RuskfileDeserializeError::InvalidGlob {
+        task: task.clone(),
+        pattern: path.to_owned(),
+        error: Box::new(error),
+    })?;
+    if files.is_empty() {
+        return Ok(vec![literal.into()]);
     }
+    // NOTE: The target itself may match the pattern, but it must not depend on itself.
+    Ok(files
+        .into_iter()
+        .filter(|file| !matches!(task, TaskKey::File(target) if target == file))
+        .collect())
+}
+
+/// Expand the glob pattern relative to the base directory into the matching files.
+/// Files ignored by .gitignore are not included.
+fn expand_glob(base: &Path, pattern: &str) -> Result<Vec<NormarizedPath>, ignore::Error> {
+    // Walk only under the leading components without wildcards
+    let components = pattern.split('/').collect_vec();
+    let split = components
+        .iter()
+        .position(|component| is_glob(component))
+        .unwrap_or(components.len());
+    let root = NormarizedPath::from(base.join(components[..split].join("/"))).to_path_buf();
+    let rest = components[split..].join("/");
+
+    let mut builder = OverrideBuilder::new(&root);
+    builder.add(&format!("/{rest}"))?; // Anchored to the root, not matching at any depth
+    let matcher = builder.build()?;
+    // NOTE: Anchored patterns in the .gitignore files above the start of a walk are misapplied,
+    // so the walk starts at the repository root, skipping the directories off the way to the root.
+    let top = repository_root(&root).unwrap_or(&root).to_owned();
+    // NOTE: The matcher is not given as the overrides of the walk, because overrides take precedence over .gitignore.
+    let mut files: Vec<_> = WalkBuilder::new(top)
+        .require_git(true)
+        .follow_links(true)
+        .filter_entry(move |entry| {
+            entry.path().starts_with(&root) || root.starts_with(entry.path())
+        })
+        .build()
+        .filter_map(Result::ok)
+        .filter(|entry| {
+            entry.file_type().is_some_and(|ft| ft.is_file())
+                && matcher.matched(entry.path(), false).is_whitelist()
+        })
+        .map(|entry| NormarizedPath::from(entry.path()))
+        .collect();
+    files.sort_by(|a, b| a.as_abs_str().cmp(b.as_abs_str()));
+    Ok(files)
 }
 
 /// serde::Deserialize of Ruskfile File content
-#[derive(serde::Deserialize)]
+#[derive(serde::Deserialize, schemars::JsonSchema)]
+#[schemars(
+    title = "rusk.toml",
+    description = "Tasks and settings of rusk",
+    deny_unknown_fields
+)]
 struct RuskfileDeserializer {
-    /// TaskDeserializers map
+    /// Version of the format the file is written in
+    #[serde(default)]
+    #[schemars(range(min = 1, max = CONFIG_VERSION))]
+    version: Option<u32>,
+    /// Tasks by their names. Names containing `/` or `.` are file tasks, and the others are phony tasks.
     #[serde(default)]
+    #[schemars(with = "std::collections::HashMap<String, TaskDeserializer>")]
     tasks: HashMap<TaskKeyRelative, TaskDeserializer>,
+    /// Global settings
+    #[serde(default)]
+    settings: SettingsDeserializer,
+    /// Content of the file, kept to locate the errors
+    #[serde(skip)]
+    text: String,
+}
+
+/// serde::Deserialize of global settings
+#[derive(serde::Deserialize, schemars::JsonSchema, Default)]
+#[schemars(
+    description = "Global settings, each taken from the shallowest rusk.toml defining it",
+    deny_unknown_fields
+)]
+struct SettingsDeserializer {
+    /// Maximum number of scripts running at once
+    #[serde(default)]
+    jobs: Option<NonZeroUsize>,
+    /// Compare content hashes instead of modified datetimes
+    #[serde(default)]
+    fingerprint: Option<bool>,
+    /// How to treat the outputs not produced by a successful run
+    #[serde(default)]
+    verify_outputs: Option<VerifyOutputs>,
+    /// Delete the outputs modified by a failed run
+    #[serde(default)]
+    delete_on_error: Option<bool>,
+    /// How to treat the unknown fields
+    #[serde(default)]
+    unknown_fields: Option<UnknownFields>,
 }
 
 /// serde::Deserialize of Each rusk Task
-#[derive(serde::Deserialize)]
+#[derive(serde::Deserialize, schemars::JsonSchema)]
+#[schemars(description = "Task", deny_unknown_fields, transform = add_aliases)]
 struct TaskDeserializer {
     /// Task Raw content
     #[serde(flatten)]
+    #[schemars(with = "TaskDeserializerInner")]
     inner: Table,
     /// Description for help
     #[serde(default)]
     description: Option<String>,
+    /// Arguments which the task accepts
+    #[serde(default, alias = "params")]
+    args: Vec<TaskArgDeserializer>,
+}
+
+/// serde::Deserialize of Each argument of a Task
+#[derive(serde::Deserialize, serde::Serialize, schemars::JsonSchema, PartialEq, Eq, PartialOrd)]
+#[schemars(description = "Argument of a task", deny_unknown_fields)]
+struct TaskArgDeserializer {
+    /// Name of the argument, also used as the environment variable name
+    name: String,
+    /// Value used when the argument is not given
+    #[serde(default)]
+    default: Option<String>,
+    /// Whether the argument must be given
+    #[serde(default)]
+    required: bool,
+    /// Description for help
+    #[serde(default)]
+    description: Option<String>,
 }
 
-#[derive(serde::Deserialize)]
+#[derive(serde::Deserialize, schemars::JsonSchema)]
 struct TaskDeserializerInner {
     /// Environment variables that are specific to this task
     #[serde(default)]
+    #[schemars(with = "std::collections::HashMap<String, String>")]
     envs: HashMap<String, String>,
     /// Script to be executed
     #[serde(default)]
     script: Option<String>,
-    /// Dependencies
+    /// Dependencies, where file dependencies may be glob patterns
     #[serde(default)]
+    #[schemars(with = "Vec<String>")]
     depends: Vec<TaskKeyRelative>,
+    /// Files checked for freshness in addition to the file dependencies, which may be glob patterns
+    #[serde(default, alias = "sources")]
+    inputs: Option<Vec<String>>,
+    /// Files produced in addition to the target
+    #[serde(default)]
+    outputs: Vec<String>,
     /// Working directory
     #[serde(default)]
+    #[schemars(extend("default" = "."))]
     cwd: Cow<'static, str>,
+    /// Compare content hashes instead of modified datetimes
+    #[serde(default)]
+    fingerprint: Option<bool>,
+    /// How to treat the outputs not produced by a successful run
+    #[serde(default)]
+    verify_outputs: Option<VerifyOutputs>,
+    /// Delete the outputs modified by a failed run
+    #[serde(default)]
+    delete_on_error: Option<bool>,
+    /// Long-running service kept alive while the dependents run
+    #[serde(default)]
+    service: bool,
+    /// Condition for the service to be regarded as ready
+    #[serde(default)]
+    ready: Option<ReadyDeserializer>,
+    /// Seconds to wait for the service to get ready
+    #[serde(default)]
+    ready_timeout: Option<u64>,
+}
+
+/// serde::Deserialize of the readiness probe of a service
+#[derive(serde::Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "lowercase")]
+#[schemars(description = "Condition for the service to be regarded as ready")]
+enum ReadyDeserializer {
+    /// TCP port on localhost accepts connections
+    Port(u16),
+    /// Line matching the regular expression is printed
+    Log(String),
+    /// File exists, relative to the working directory
+    File(String),
 }
 
 impl Default for TaskDeserializerInner {
@@ -385,7 +1230,15 @@ impl Default for TaskDeserializerInner {
             envs: Default::default(),
             script: Default::default(),
             depends: Default::default(),
+            inputs: Default::default(),
+            outputs: Default::default(),
             cwd: Cow::Borrowed("."),
+            fingerprint: Default::default(),
+            verify_outputs: Default::default(),
+            delete_on_error: Default::default(),
+            service: Default::default(),
+            ready: Default::default(),
+            ready_timeout: Default::default(),
         }
     }
 }
@@ -1,22 +1,34 @@
 use std::{
     fmt::Display,
     io::{BufWriter, IsTerminal, Write},
+    num::NonZeroUsize,
+    path::PathBuf,
     time::Duration,
 };
 
-use args::Args;
+use args::{parse_jobs, Args, ArgsError, GraphFormat, ListFormat, Mode, HELP, JOBS_ENV};
+use check::Severity;
 use colored::Colorize;
+use diagnostic::Diagnostic;
 use fs::RuskfileComposer;
 use itertools::Itertools;
-use path::get_current_dir;
-use rusk::{Rusk, RuskError, TaskError};
+use path::{get_current_dir, NormarizedPath};
+use rusk::{ExecuteOpts, PlanAction, PlanEntry, Rusk, RuskError, TaskError};
+use service::Services;
+use watch::Watcher;
 
 mod args;
+mod check;
+mod diagnostic;
+mod diagram;
 mod digraph;
 mod fs;
 mod path;
 mod rusk;
+mod service;
+mod state;
 mod taskkey;
+mod watch;
 
 /// Abort the program with a message.
 #[cold]
@@ -25,27 +37,124 @@ fn abort(title: &'static str, msg: impl Display, code: i32) -> ! {
     std::process::exit(code);
 }
 
+/// Version of the schema of the machine-readable task list, bumped on incompatible changes.
+const LIST_SCHEMA_VERSION: u32 = 1;
+
 /// Timeout for scanning the directory.
 const SCAN_TIMEOUT: Duration = Duration::from_millis(500);
 
+/// Load the given config files, or search for rusk.toml files if none is given.
+async fn load_composer(files: &[PathBuf]) -> RuskfileComposer {
+    let mut composer = RuskfileComposer::new();
+    if files.is_empty() {
+        // TODO: Config to select either Project root or Current dir as root
+        if tokio::time::timeout(SCAN_TIMEOUT, composer.walkdir(get_current_dir()))
+            .await
+            .is_err()
+        {
+            abort(
+                "abort",
+                format_args!("Scan took over {SCAN_TIMEOUT:?}. Try in deeper directory."),
+                1,
+            );
+        }
+    } else {
+        if let Some(file) = files.iter().find(|file| !file.is_file()) {
+            abort(
+                "error",
+                format_args!("Config file {} not found", file.display()),
+                1,
+            );
+        }
+        composer.load(files.iter().map(NormarizedPath::from)).await;
+    }
+    composer
+}
+
 #[tokio::main]
 async fn main() {
-    let args = Args::new();
+    let mut args = match Args::new() {
+        Ok(args) => args,
+        Err(err) => abort("error", err, 1),
+    };
 
-    let mut composer = RuskfileComposer::new();
-    // TODO: Config to select either Project root or Current dir as root
-    if tokio::time::timeout(SCAN_TIMEOUT, composer.walkdir(get_current_dir()))
-        .await
-        .is_err()
-    {
-        abort(
-            "abort",
-            format_args!("Scan took over {SCAN_TIMEOUT:?}. Try in deeper directory."),
-            1,
-        );
+    match args.mode {
+        Mode::Help => {
+            print!("{HELP}");
+            return;
+        }
+        Mode::Version => {
+            println!("rusk {}", env!("CARGO_PKG_VERSION"));
+            return;
+        }
+        Mode::Schema => {
+            let schema = serde_json::to_string_pretty(&fs::json_schema()).unwrap();
+            println!("{schema}");
+            return;
+        }
+        _ => {}
+    }
+
+    if let Some(dir) = &args.directory {
+        if let Err(err) = std::env::set_current_dir(dir) {
+            abort(
+                "error",
+                format_args!("Cannot change directory to {}: {err}", dir.display()),
+                1,
+            );
+        }
+    }
+
+    let composer = load_composer(&args.files).await;
+
+    if args.mode == Mode::List && args.format != ListFormat::Text {
+        let (tasks, errors) = composer.tasks_info();
+        let mut stdout = BufWriter::new(std::io::stdout().lock());
+        if args.format == ListFormat::Json {
+            let list = serde_json::json!({
+                "version": LIST_SCHEMA_VERSION,
+                "tasks": tasks,
+                "errors": errors,
+            });
+            serde_json::to_writer(&mut stdout, &list).unwrap();
+            writeln!(stdout).unwrap();
+        } else {
+            // The type of each line is tagged, since tasks and errors are mixed
+            for task in tasks {
+                let mut line = serde_json::to_value(task).unwrap();
+                line["type"] = "task".into();
+                writeln!(stdout, "{line}").unwrap();
+            }
+            for error in errors {
+                let mut line = serde_json::to_value(error).unwrap();
+                line["type"] = "error".into();
+                writeln!(stdout, "{line}").unwrap();
+            }
+        }
+        stdout.flush().unwrap();
+        return;
     }
 
-    if args.no_pargs() {
+    if args.mode == Mode::Check {
+        let findings = check::check(composer);
+        let errors = findings
+            .iter()
+            .filter(|finding| finding.severity == Severity::Error)
+            .count();
+        let warnings = findings.len() - errors;
+        for finding in &findings {
+            eprintln!("{finding}\n");
+        }
+        if findings.is_empty() {
+            eprintln!("No problems found");
+        } else {
+            eprintln!("{errors} error(s) and {warnings} warning(s) found");
+        }
+        // Only errors fail the check, so that warnings do not break CI
+        std::process::exit(i32::from(errors > 0));
+    }
+
+    if args.mode == Mode::List {
         {
             let stdout = std::io::stdout();
             let is_tty = stdout.is_terminal();
@@ -75,37 +184,206 @@ async fn main() {
             for err in composer.errors_list().sorted() {
                 writeln!(stderr, "\n  {}", err.into_verbose()).unwrap();
             }
+            // Reported as warnings, since the tasks are listed anyway
+            for err in composer.schema_errors() {
+                writeln!(stderr, "\n{}: {err}", "warning".on_yellow().black().bold()).unwrap();
+            }
             stderr.flush().unwrap();
         }
         return;
     }
 
-    let res: Result<(), MainError> = async move {
-        let composer = Rusk::try_from(composer)?;
-        composer.exec(args, Default::default()).await?;
-        Ok(())
-    }
-    .await;
+    // Services are spawned as local tasks, since the shell is not Send
+    let local = tokio::task::LocalSet::new();
+    let res: Result<(), MainError> = local
+        .run_until(async move {
+            let jobs = match args.jobs {
+                Some(jobs) => Some(jobs),
+                None => std::env::var(JOBS_ENV)
+                    .ok()
+                    .map(|value| parse_jobs(&value))
+                    .transpose()?,
+            };
+            // Combinations with the other modes are rejected when parsed
+            if args.watch {
+                return watch(composer, args, jobs).await;
+            }
+            let composer = Rusk::try_from(composer)?;
+            let opts = ExecuteOpts {
+                extra_args: args.take_extra(),
+                jobs,
+                keep_going: args.keep_going,
+                ..Default::default()
+            };
+            if args.mode == Mode::Graph {
+                let format = args.graph_format;
+                let diagram = composer.diagram(args.into_iter().collect(), opts).await?;
+                match format {
+                    GraphFormat::Dot => print!("{}", diagram.dot()),
+                    GraphFormat::Mermaid => print!("{}", diagram.mermaid()),
+                }
+            } else if args.mode == Mode::Clean {
+                let dry_run = args.dry_run;
+                let files = composer.generated_files(args)?;
+                let tracked = fs::git_tracked(&files);
+                let mut stdout = BufWriter::new(std::io::stdout().lock());
+                for file in files {
+                    if !file.exists() {
+                        continue;
+                    }
+                    if tracked.contains(&file) {
+                        // Protect the plain sources which happen to be named as targets
+                        writeln!(
+                            stdout,
+                            "{} {}  {}",
+                            "keep".dimmed(),
+                            file.as_short_str().bright_blue().bold(),
+                            "tracked by git".dimmed()
+                        )
+                        .unwrap();
+                        continue;
+                    }
+                    writeln!(
+                        stdout,
+                        "{} {}",
+                        "rm  ".red().bold(),
+                        file.as_short_str().bright_blue().bold()
+                    )
+                    .unwrap();
+                    if !dry_run {
+                        std::fs::remove_file(&file)
+                            .map_err(|error| MainError::CleanFailed { file, error })?;
+                    }
+                }
+                stdout.flush().unwrap();
+            } else if args.dry_run {
+                let plan = composer.plan(args, opts).await?;
+                let mut stdout = BufWriter::new(std::io::stdout().lock());
+                for entry in plan.iter() {
+                    writeln!(stdout, "{}", entry).unwrap();
+                }
+                stdout.flush().unwrap();
+                if let Some(PlanEntry {
+                    action: PlanAction::Fail(err),
+                    ..
+                }) = plan
+                    .into_iter()
+                    .find(|entry| matches!(entry.action, PlanAction::Fail(_)))
+                {
+                    Err(RuskError::from(err))?;
+                }
+            } else {
+                composer.exec(args, opts).await?;
+            }
+            Ok(())
+        })
+        .await;
 
     if let Err(err) = res {
         let (title, code) = match &err {
-            MainError::RuskError(RuskError::TaskFailed(TaskError::Execution {
-                exit_code,
-                key: _,
-            })) => ("abort", *exit_code),
+            MainError::RuskError(RuskError::TaskFailed(err)) => match err.as_ref() {
+                TaskError::Execution { exit_code, .. } => ("abort", *exit_code),
+                _ => ("error", 1),
+            },
+            MainError::RuskError(RuskError::Interrupted(_)) => ("abort", 130),
+            MainError::RuskError(RuskError::TasksFailed { failures, .. }) => {
+                // Use the exit code only if all the failures agree on it
+                let codes = failures
+                    .iter()
+                    .map(|err| match err {
+                        TaskError::Execution { exit_code, .. } => *exit_code,
+                        _ => 1,
+                    })
+                    .dedup()
+                    .collect_vec();
+                ("abort", if let [code] = codes[..] { code } else { 1 })
+            }
             _ => ("error", 1),
         };
         abort(title, err, code);
     }
 }
 
+/// Run the targets, and run them again whenever their source files or the config files change.
+/// Errors are reported in each cycle without stopping, except for the interruption.
+async fn watch(
+    mut composer: RuskfileComposer,
+    mut args: Args,
+    jobs: Option<NonZeroUsize>,
+) -> Result<(), MainError> {
+    let extra_args = args.take_extra();
+    let keep_going = args.keep_going;
+    let files = std::mem::take(&mut args.files);
+    let targets = args.into_iter().collect_vec();
+    // Services are kept running across the cycles, and restarted only when their sources change
+    let services = Services::default();
+    loop {
+        // Each cycle starts from the config files loaded again, so the states of the tasks are fresh.
+        let mut watched = composer.paths().cloned().collect_vec();
+        for err in composer.errors_list().sorted() {
+            eprintln!(
+                "{}: Cannot load {}",
+                "warning".on_yellow().black().bold(),
+                err.into_verbose()
+            );
+        }
+        let mut watcher = None;
+        let cycle = async {
+            let rusk = Rusk::try_from(composer)?;
+            watched.extend(rusk.source_files(&targets)?);
+            // Snapshot taken before running, so that the files saved during the run trigger the next one
+            watcher = Some(Watcher::new(std::mem::take(&mut watched)));
+            let opts = ExecuteOpts {
+                extra_args: extra_args.clone(),
+                jobs,
+                keep_going,
+                services: Some(services.clone()),
+                ..Default::default()
+            };
+            rusk.exec(targets.clone(), opts).await?;
+            Ok::<_, MainError>(())
+        };
+        match cycle.await {
+            Err(err @ MainError::RuskError(RuskError::Interrupted(_))) => {
+                services.stop_all().await;
+                return Err(err);
+            }
+            Err(err) => eprintln!("{}: {err}", "error".bold().red()),
+            Ok(()) => {}
+        }
+        let mut watcher = watcher.unwrap_or_else(|| Watcher::new(watched));
+        eprintln!(
+            "{}: Watching {} files. Press Ctrl-C to stop.",
+            "watch".cyan().bold(),
+            watcher.len()
+        );
+        tokio::select! {
+            _ = watcher.changed() => {}
+            _ = tokio::signal::ctrl_c() => {
+                services.stop_all().await;
+                return Ok(());
+            }
+        }
+        composer = load_composer(&files).await;
+    }
+}
+
 /// Main error type.
 #[derive(Debug, thiserror::Error)]
 enum MainError {
     /// Error when converting RuskfileComposer to Rusk.
     #[error(transparent)]
-    RuskfileDeserializeError(#[from] fs::RuskfileDeserializeError),
+    RuskfileDeserializeError(#[from] Diagnostic<fs::RuskfileDeserializeError>),
     /// Rusk error.
     #[error(transparent)]
     RuskError(#[from] RuskError),
+    /// Failed to remove a generated file.
+    #[error("Cannot remove {file}: {error}")]
+    CleanFailed {
+        file: NormarizedPath,
+        error: std::io::Error,
+    },
+    /// Invalid value in the environment variables.
+    #[error("Invalid environment variable: {0}")]
+    InvalidEnv(#[from] ArgsError),
 }
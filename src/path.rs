@@ -139,6 +139,21 @@ impl<'a, T: Into<Cow<'a, Path>>> From<T> for NormarizedPath {
     }
 }
 
+/// Returns the root of the git repository containing the directory, which is the nearest ancestor with `.git`.
+pub fn repository_root(dir: &Path) -> Option<&Path> {
+    dir.ancestors().find(|dir| dir.join(".git").exists())
+}
+
+/// Returns the project root: the root of the git repository containing the current directory,
+/// or the current directory itself outside of any repository.
+pub fn get_project_root() -> &'static NormarizedPath {
+    static ROOT: OnceCell<NormarizedPath> = OnceCell::new();
+    ROOT.get_or_init(|| {
+        let cwd = get_current_dir();
+        repository_root(cwd).map_or_else(|| cwd.clone(), NormarizedPath::from)
+    })
+}
+
 /// Returns the current directory as a normalized path.
 pub fn get_current_dir() -> &'static NormarizedPath {
     static CWD: OnceCell<NormarizedPath> = OnceCell::new();
@@ -1,18 +1,31 @@
 use std::{
     cell::{Ref, RefCell},
-    fmt::Debug,
+    fmt::{Debug, Display},
+    num::NonZeroUsize,
     ops::Deref,
+    path::Path,
+    rc::Rc,
+    time::{Duration, SystemTime},
 };
 
-use deno_task_shell::{parser::SequentialList, ShellPipeReader, ShellPipeWriter, ShellState};
-use futures::future::try_join_all;
-use hashbrown::HashMap;
-use tokio::sync::watch::Receiver;
+use colored::Colorize;
+use deno_task_shell::{
+    parser::SequentialList, KillSignal, ShellPipeReader, ShellPipeWriter, ShellState, SignalKind,
+};
+use futures::future::join_all;
+use hashbrown::{HashMap, HashSet};
+use itertools::{EitherOrBoth, Itertools};
+use tokio::sync::{watch::Receiver, Semaphore};
 
 use crate::{
+    check::Finding,
+    diagnostic::{Diagnostic, Span, Step, TaskOrigin, ARGS},
+    diagram::{Diagram, DiagramNode, NodeKind},
     digraph::{DigraphItem, TreeNode, TreeNodeCreationError},
     fs::{RuskfileComposer, RuskfileDeserializeError},
-    path::{get_current_dir, NormarizedPath},
+    path::{get_current_dir, get_project_root, NormarizedPath},
+    service::{scan_output, ReadyProbe, ServiceConfig, Services, Stamp},
+    state::{StateStore, TaskRecord},
     taskkey::{TaskKey, TaskKeyParseError, TaskKeyRelative},
 };
 
@@ -26,13 +39,161 @@ pub enum RuskError {
     InvalidArgument(#[from] TaskKeyParseError),
     /// TreeNode creation error
     #[error(transparent)]
-    TreeNodeBroken(#[from] TreeNodeCreationError<TaskKey>),
+    TreeNodeBroken(Box<TreeNodeCreationError<TaskKey>>),
     /// Task parsing error
     #[error(transparent)]
-    TaskUnparsable(#[from] TaskParseError),
+    TaskUnparsable(#[from] Diagnostic<TaskParseError>),
     /// Task execution error
     #[error(transparent)]
-    TaskFailed(#[from] TaskError),
+    TaskFailed(Box<TaskError>),
+    /// Task execution errors with the tasks affected by them
+    #[error("{} task(s) failed:{}{}{}",
+        failures.len(),
+        failures.iter().map(|err| format!("\n  - {err}")).join(""),
+        if interrupted.is_empty() {
+            String::new()
+        } else {
+            format!("\nInterrupted: {}", interrupted.iter().join(", "))
+        },
+        if skipped.is_empty() {
+            String::new()
+        } else {
+            format!("\nSkipped because of the failures: {}", skipped.iter().join(", "))
+        },
+    )]
+    TasksFailed {
+        failures: Vec<TaskError>,
+        interrupted: Vec<TaskKey>,
+        skipped: Vec<TaskKey>,
+    },
+    /// Unknown task given as a target or a dependency
+    #[error("{0}")]
+    TaskNotFound(Box<TaskNotFound>),
+    /// Circular dependencies
+    #[error("{} cycle(s) found in the dependencies:{}",
+        .0.len(),
+        .0.iter().map(|cycle| format!("\n  - {cycle}")).join(""),
+    )]
+    CircularDependency(Vec<DependencyCycle>),
+    /// Failed to save the state file
+    #[error("Failed to save the state: {0}")]
+    StateNotSaved(std::io::Error),
+    /// Execution interrupted by Ctrl-C
+    #[error("Interrupted{}",
+        if .0.is_empty() {
+            String::new()
+        } else {
+            format!(": {}", .0.iter().join(", "))
+        },
+    )]
+    Interrupted(Vec<TaskKey>),
+}
+
+// NOTE: Boxed to keep the results small, while converted implicitly like the other variants
+impl From<TreeNodeCreationError<TaskKey>> for RuskError {
+    fn from(err: TreeNodeCreationError<TaskKey>) -> Self {
+        Self::TreeNodeBroken(Box::new(err))
+    }
+}
+
+impl From<TaskError> for RuskError {
+    fn from(err: TaskError) -> Self {
+        Self::TaskFailed(Box::new(err))
+    }
+}
+
+/// Circular dependency with where each edge comes from
+#[derive(Debug)]
+pub struct DependencyCycle(Vec<CycleEdge>);
+
+/// Edge of a circular dependency
+#[derive(Debug)]
+struct CycleEdge {
+    /// Dependent
+    from: TaskKey,
+    /// Dependency
+    to: TaskKey,
+    /// Where the edge is defined
+    span: Option<Span>,
+    /// Whether the edge is from an additional output to the task producing it
+    output: bool,
+}
+
+impl DependencyCycle {
+    /// Explain the cycle with the rusk.toml defining each task.
+    fn new(
+        cycle: Vec<TaskKey>,
+        origins: &HashMap<TaskKey, TaskOrigin>,
+        aliases: &HashMap<TaskKey, TaskKey>,
+    ) -> Self {
+        let edges = cycle
+            .iter()
+            .tuple_windows()
+            .map(|(from, to)| {
+                let output = aliases.get(from) == Some(to);
+                // The edge from an output is defined in the task producing it
+                let span = if output {
+                    origins
+                        .get(to)
+                        .and_then(|origin| origin.locate_output(from))
+                } else {
+                    origins
+                        .get(from)
+                        .and_then(|origin| origin.locate_dependency(to))
+                };
+                CycleEdge {
+                    from: from.clone(),
+                    to: to.clone(),
+                    span,
+                    output,
+                }
+            })
+            .collect();
+        Self(edges)
+    }
+}
+
+impl Display for DependencyCycle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        ////////////////////////////////////////////////
+        //
+        // Format:
+        //     (task_name) -> (task_name) -> ... -> (task_name)
+        //         (task_name) depends on (task_name)  in (path):(line):(col)
+        //
+        ////////////////////////////////////////////////
+
+        let Self(edges) = self;
+        if let Some(first) = edges.first() {
+            write!(f, "{}", first.from)?;
+        }
+        for edge in edges {
+            write!(f, " -> {}", edge.to)?;
+        }
+        for CycleEdge {
+            from,
+            to,
+            span,
+            output,
+        } in edges
+        {
+            let relation = if *output {
+                "is an output of"
+            } else {
+                "depends on"
+            };
+            write!(f, "\n      {from} {relation} {to}")?;
+            if let Some(span) = span {
+                write!(
+                    f,
+                    "  {} {}",
+                    "in".dimmed().italic(),
+                    span.location().to_string().yellow().dimmed().italic()
+                )?;
+            }
+        }
+        Ok(())
+    }
 }
 
 /// IO set about deno_task_shell
@@ -57,44 +218,594 @@ impl Default for IOSet {
 pub struct Rusk {
     /// Tasks to be executed
     tasks: HashMap<TaskKey, Task>,
+    /// Templates of file tasks without an exact match
+    rules: Vec<PatternRule>,
+    /// Additional outputs of the tasks, mapped to the tasks producing them
+    aliases: HashMap<TaskKey, TaskKey>,
+    /// Global settings
+    settings: Settings,
+}
+
+/// Global settings given in rusk.toml
+#[derive(Default)]
+pub struct Settings {
+    /// Maximum number of scripts running at once
+    pub jobs: Option<NonZeroUsize>,
+    /// Compare content hashes instead of modified datetimes
+    pub fingerprint: Option<bool>,
+    /// How to treat the outputs not produced by a successful run
+    pub verify_outputs: Option<VerifyOutputs>,
+    /// Delete the outputs modified by a failed run
+    pub delete_on_error: Option<bool>,
+    /// How to treat the unknown fields in rusk.toml
+    pub unknown_fields: Option<UnknownFields>,
+}
+
+/// How to treat the outputs which are missing or older than the dependencies after a successful run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum VerifyOutputs {
+    /// Not checked
+    Off,
+    /// Print a warning
+    #[default]
+    Warn,
+    /// Fail the task
+    Error,
+}
+
+/// How to treat the unknown fields in rusk.toml, which are likely to be typos
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum UnknownFields {
+    /// Print a warning
+    Warn,
+    /// Refuse to run
+    #[default]
+    Error,
 }
 
 impl TryFrom<RuskfileComposer> for Rusk {
-    type Error = RuskfileDeserializeError;
+    type Error = Diagnostic<RuskfileDeserializeError>;
     fn try_from(value: RuskfileComposer) -> Result<Self, Self::Error> {
-        Ok(Rusk {
-            tasks: value.try_into()?,
-        })
+        let settings = value.settings();
+        let (tasks, rules) = value.try_into()?;
+        Ok(Rusk::new(tasks, rules, settings))
     }
 }
 
 impl Rusk {
+    /// Tasks and pattern rules, with the additional outputs of the tasks as their aliases
+    pub fn new(tasks: HashMap<TaskKey, Task>, rules: Vec<PatternRule>, settings: Settings) -> Self {
+        let aliases = tasks
+            .iter()
+            .flat_map(|(key, task)| {
+                task.outputs
+                    .iter()
+                    .map(|output| (TaskKey::File(output.clone()), key.clone()))
+            })
+            .collect();
+        Rusk {
+            tasks,
+            rules,
+            aliases,
+            settings,
+        }
+    }
     /// Execute tasks
     pub async fn exec(
         self,
-        args: impl IntoIterator<Item = String>,
-        opts: ExecuteOpts,
+        targets: impl IntoIterator<Item = Target>,
+        mut opts: ExecuteOpts,
     ) -> Result<(), RuskError> {
-        let Rusk { tasks } = self;
-        let tasks = into_executable(tasks, opts)?;
-        let tk = args
+        let keep_going = opts.keep_going;
+        let kill_signal = opts.kill_signal.clone();
+        let managed = opts.services.is_some();
+        let services = opts.services.get_or_insert_with(Default::default).clone();
+        let state = Rc::new(RefCell::new(StateStore::load(get_project_root())));
+        let graph = self.graph(targets, opts, Rc::clone(&state))?;
+        let roots = graph.iter().map(|root| root.key.clone()).collect_vec();
+        let keys = TreeNode::post_order(&graph)
+            .into_iter()
+            .map(|node| node.key.clone())
+            .collect_vec();
+        let res = exec_all(graph, keep_going, kill_signal).await;
+        // Save the state even if some tasks failed, to keep the results of the others
+        let saved = state.borrow().save(get_project_root());
+        if managed {
+            // Services no longer needed are stopped, and the others are left to the caller.
+            services.stop_except(&keys.iter().collect()).await;
+        } else if res.is_err() {
+            services.stop_all().await;
+        } else {
+            let waited = wait_services(&services, roots).await;
+            services.stop_all().await;
+            waited?;
+        }
+        res?;
+        saved.map_err(RuskError::StateNotSaved)
+    }
+
+    /// Make the execution plan of tasks without executing them.
+    /// Entries are listed in the order of execution.
+    pub async fn plan(
+        self,
+        targets: impl IntoIterator<Item = Target>,
+        opts: ExecuteOpts,
+    ) -> Result<Vec<PlanEntry>, RuskError> {
+        let state = Rc::new(RefCell::new(StateStore::load(get_project_root())));
+        let graph = self.graph(targets, opts, state)?;
+        Ok(plan_all(&graph).await)
+    }
+
+    /// Dependency graph of the targets, or of all the tasks if no target is given.
+    /// Each node is checked whether up to date, as in the dry run.
+    pub async fn diagram(
+        mut self,
+        targets: Vec<Target>,
+        opts: ExecuteOpts,
+    ) -> Result<Diagram, RuskError> {
+        let targets = if targets.is_empty() {
+            self.tasks
+                .keys()
+                .chain(self.aliases.keys())
+                .map(|key| Target {
+                    name: key.as_ref().to_owned(),
+                    params: Vec::new(),
+                })
+                .sorted_by(|a, b| a.name.cmp(&b.name))
+                .collect()
+        } else {
+            targets
+        };
+        // Required arguments not given are left unset, so that the graph can be drawn without them
+        let rule_tasks = self.rules.iter_mut().map(|rule| &mut rule.task);
+        for task in self.tasks.values_mut().chain(rule_tasks) {
+            task.args.iter_mut().for_each(|arg| arg.required = false);
+        }
+        let tk = Target::keys(&targets)?;
+        let mut configs: HashMap<_, _> =
+            closure(self.tasks.clone(), &self.rules, &self.aliases, &tk)
+                .into_iter()
+                .map(|(key, task)| (key, task.origin.config().clone()))
+                .collect();
+        for (alias, primary) in &self.aliases {
+            if let Some(config) = configs.get(primary).cloned() {
+                configs.insert(alias.clone(), config);
+            }
+        }
+        let aliases = self.aliases.clone();
+
+        let state = Rc::new(RefCell::new(StateStore::load(get_project_root())));
+        let graph = self.graph(targets, opts, state)?;
+        let order = TreeNode::post_order(&graph);
+        let index: HashMap<_, _> = order
+            .iter()
+            .enumerate()
+            .map(|(i, node)| (&node.key, i))
+            .collect();
+        let edges = order
+            .iter()
+            .flat_map(|node| {
+                node.children
+                    .iter()
+                    .map(|child| (index[&node.key], index[&child.key]))
+            })
+            .unique()
+            .collect();
+        let fresh: HashMap<_, _> = plan_all(&graph)
+            .await
             .into_iter()
-            .map({
-                fn f(s: String) -> Result<TaskKey, TaskKeyParseError> {
-                    let key = TaskKeyRelative::try_from(s)?;
-                    Ok(key.into_task_key(get_current_dir()))
+            .map(|entry| {
+                let fresh = matches!(
+                    entry.action,
+                    PlanAction::Skip | PlanAction::Source { exists: true }
+                );
+                (entry.key, fresh)
+            })
+            .collect();
+        let nodes = order
+            .iter()
+            .map(|node| {
+                let key = node.key.clone();
+                // Additional outputs are as fresh as the tasks producing them
+                let up_to_date = fresh[aliases.get(&key).unwrap_or(&key)];
+                let config = configs.remove(&key);
+                let kind = match (&key, &config) {
+                    (TaskKey::Phony(_), _) => NodeKind::Phony,
+                    (TaskKey::File(_), Some(_)) => NodeKind::File,
+                    (TaskKey::File(_), None) => NodeKind::Source,
+                };
+                DiagramNode {
+                    key,
+                    kind,
+                    config,
+                    up_to_date,
+                }
+            })
+            .collect();
+        Ok(Diagram { nodes, edges })
+    }
+
+    /// Files generated by the tasks with a script, sorted.
+    /// If targets are given, only the tasks reachable from them are included.
+    pub fn generated_files(
+        self,
+        targets: impl IntoIterator<Item = Target>,
+    ) -> Result<Vec<NormarizedPath>, RuskError> {
+        let Rusk {
+            tasks,
+            rules,
+            aliases,
+            ..
+        } = self;
+        let tk = Target::keys(&targets.into_iter().collect_vec())?;
+        let tasks = if tk.is_empty() {
+            let mut tasks = tasks;
+            // Files made by the pattern rules are found among the outputs recorded by the past runs,
+            // so that the files which only happen to match a rule are never removed
+            let state = StateStore::load(get_project_root());
+            for file in state
+                .outputs()
+                .map(|output| NormarizedPath::from(Path::new(output)))
+            {
+                let key = TaskKey::File(file.clone());
+                if tasks.contains_key(&key)
+                    || aliases.contains_key(&key)
+                    || rules.iter().all(|rule| rule.stem(&file).is_none())
+                {
+                    continue;
                 }
-                f
+                if let Some(task) = resolve_rule(&rules, &file, |dep| tasks.contains_key(dep)) {
+                    tasks.insert(key, task);
+                }
+            }
+            tasks
+        } else {
+            let defined = defined_tasks(&tasks, &aliases);
+            let tasks = closure(tasks, &rules, &aliases, &tk);
+            if let Some(key) = tk
+                .into_iter()
+                .find(|key| !tasks.contains_key(key) && !aliases.contains_key(key))
+            {
+                let err = TaskNotFound::new(key, None, &defined);
+                return Err(RuskError::TaskNotFound(Box::new(err)));
+            }
+            tasks
+        };
+        Ok(tasks
+            .into_iter()
+            .filter(|(_, task)| task.script.as_ref().is_some_and(|s| !s.trim().is_empty()))
+            .flat_map(|(key, task)| key.as_file().cloned().into_iter().chain(task.outputs))
+            .sorted_by(|a, b| a.as_abs_str().cmp(b.as_abs_str()))
+            .dedup()
+            .collect())
+    }
+
+    /// Source files of the tasks reachable from the targets, which are not generated by any of them.
+    /// Glob patterns are expanded when the config is loaded, so newly created files are not included.
+    pub fn source_files(&self, targets: &[Target]) -> Result<Vec<NormarizedPath>, RuskError> {
+        let tk = Target::keys(targets)?;
+        let tasks = closure(self.tasks.clone(), &self.rules, &self.aliases, &tk);
+        let generated: HashSet<_> = tasks
+            .iter()
+            .flat_map(|(key, task)| key.as_file().into_iter().chain(&task.outputs))
+            .collect();
+        Ok(tasks
+            .values()
+            .flat_map(|task| {
+                let depends = task.depends.iter().filter_map(TaskKey::as_file);
+                depends.chain(task.inputs.iter().flatten())
+            })
+            .filter(|file| !generated.contains(file))
+            .unique()
+            .cloned()
+            .collect())
+    }
+
+    /// Find the problems of all the tasks without executing anything
+    pub fn check(&self) -> Vec<Finding> {
+        /// Dependencies of a task as a vertex of the graph
+        struct Vertex(Vec<TaskKey>);
+        impl DigraphItem<TaskKey> for Vertex {
+            fn children(&self) -> impl Deref<Target = [TaskKey]> {
+                self.0.as_slice()
+            }
+        }
+
+        let mut findings = Vec::new();
+        let keys = self
+            .tasks
+            .keys()
+            .cloned()
+            .sorted_by(|a, b| a.as_ref().cmp(b.as_ref()))
+            .collect_vec();
+        // Including the tasks made from the pattern rules, which the dependencies may rely on
+        let tasks = closure(self.tasks.clone(), &self.rules, &self.aliases, &keys);
+        let defined = defined_tasks(&self.tasks, &self.aliases);
+        let is_known = |dep: &TaskKey| tasks.contains_key(dep) || self.aliases.contains_key(dep);
+        let dependencies = |task: &Task| {
+            let inputs = task.inputs.iter().flatten().cloned().map(TaskKey::File);
+            task.depends
+                .iter()
+                .cloned()
+                .chain(inputs)
+                .unique()
+                .collect_vec()
+        };
+
+        for key in &keys {
+            let task = &self.tasks[key];
+            if let Err(err) = parse_script(key, task) {
+                findings.push(Finding::error(err));
+            }
+            if !task.cwd.is_dir() {
+                let span = task.origin.locate(&[Step::Key("cwd")]);
+                let err = TaskParseError::DirectoryNotFound(task.cwd.clone());
+                findings.push(Finding::error(Diagnostic::new(err, span)));
+            }
+            for dep in dependencies(task) {
+                if is_known(&dep) {
+                    continue;
+                }
+                let span = task.origin.locate_dependency(&dep);
+                match dep {
+                    TaskKey::File(file) if file.exists() => {}
+                    TaskKey::File(file) => {
+                        let err = TaskError::dependency_not_found(key, &file, span);
+                        findings.push(Finding::error(err));
+                    }
+                    TaskKey::Phony(_) => {
+                        let err = TaskNotFound::new(dep, Some((key.clone(), span)), &defined);
+                        findings.push(Finding::error(err));
+                    }
+                }
+            }
+            if let TaskKey::File(file) = key {
+                if task.script.is_none() && !file.exists() {
+                    let err = format!("File task {key} has no script, and the file does not exist");
+                    let hint = "add a script producing the file, or create it";
+                    let span = task.origin.locate(&[]);
+                    findings.push(Finding::warning(Diagnostic::new(err, span).with_hint(hint)));
+                }
+            }
+            // Same condition as the freshness check, which runs such a task every time
+            let has_outputs = matches!(key, TaskKey::File(_)) || !task.outputs.is_empty();
+            let phony = task
+                .depends
+                .iter()
+                .find(|dep| matches!(dep, TaskKey::Phony(_)));
+            if let Some(dep) = phony.filter(|_| has_outputs && task.inputs.is_none()) {
+                let err = format!("Task {key} always runs, since it depends on phony task {dep}");
+                let hint = "list the files to check in `inputs`, so that phony dependencies only decide the order";
+                let span = task.origin.locate_dependency(dep);
+                findings.push(Finding::warning(Diagnostic::new(err, span).with_hint(hint)));
+            }
+        }
+        for rule in &self.rules {
+            if let Err(err) = parse_script(&rule.target(), &rule.task) {
+                findings.push(Finding::error(err));
+            }
+        }
+
+        // Unknown dependencies are left out, since they are reported above
+        let mut graph = HashMap::new();
+        for (key, task) in &tasks {
+            let deps = dependencies(task)
+                .into_iter()
+                .filter(|dep| is_known(dep) || matches!(dep, TaskKey::File(_)))
+                .collect_vec();
+            for dep in &deps {
+                graph.entry_ref(dep).or_insert_with(|| Vertex(Vec::new()));
+            }
+            graph.insert(key.clone(), Vertex(deps));
+        }
+        for (alias, primary) in &self.aliases {
+            graph.insert(alias.clone(), Vertex(vec![primary.clone()]));
+        }
+        if let Err(TreeNodeCreationError::CircularDependency(cycles)) =
+            TreeNode::new_vec(graph, &keys)
+        {
+            let origins: HashMap<_, _> = tasks
+                .iter()
+                .map(|(key, task)| (key.clone(), task.origin.clone()))
+                .collect();
+            findings.extend(cycles.into_iter().map(|cycle| {
+                let cycle = DependencyCycle::new(cycle, &origins, &self.aliases);
+                Finding::error(format_args!("Circular dependency: {cycle}"))
+            }));
+        }
+        findings
+    }
+
+    /// Build the dependency trees of the targets
+    fn graph(
+        self,
+        targets: impl IntoIterator<Item = Target>,
+        opts: ExecuteOpts,
+        state: Rc<RefCell<StateStore>>,
+    ) -> Result<Vec<TaskTree>, RuskError> {
+        let Rusk {
+            tasks,
+            rules,
+            aliases,
+            settings,
+        } = self;
+        let mut tk = Vec::new();
+        let mut params: HashMap<TaskKey, Vec<(String, String)>> = HashMap::new();
+        for target in targets {
+            let key = target.key()?;
+            params.entry_ref(&key).or_default().extend(target.params);
+            tk.push(key);
+        }
+        let defined = defined_tasks(&tasks, &aliases);
+        let tasks = closure(tasks, &rules, &aliases, &tk);
+        // Kept to explain the errors
+        let origins: HashMap<_, _> = tasks
+            .iter()
+            .map(|(key, task)| (key.clone(), task.origin.clone()))
+            .collect();
+        let tasks = into_executable(tasks, aliases.clone(), params, &settings, opts, state)?;
+        match TreeNode::new_vec(tasks, tk) {
+            Err(TreeNodeCreationError::ItemNotFound { key, parent }) => {
+                let parent = parent.map(|parent| {
+                    let span = origins
+                        .get(&parent)
+                        .and_then(|origin| origin.locate_dependency(&key));
+                    (parent, span)
+                });
+                let err = TaskNotFound::new(key, parent, &defined);
+                Err(RuskError::TaskNotFound(Box::new(err)))
+            }
+            Err(TreeNodeCreationError::CircularDependency(cycles)) => {
+                Err(RuskError::CircularDependency(
+                    cycles
+                        .into_iter()
+                        .map(|cycle| DependencyCycle::new(cycle, &origins, &aliases))
+                        .collect(),
+                ))
+            }
+            res => Ok(res?),
+        }
+    }
+}
+
+/// Tasks defined in the config files with the rusk.toml defining them, including the additional outputs
+fn defined_tasks(
+    tasks: &HashMap<TaskKey, Task>,
+    aliases: &HashMap<TaskKey, TaskKey>,
+) -> Vec<(TaskKey, NormarizedPath)> {
+    let outputs = aliases
+        .iter()
+        .filter_map(|(alias, primary)| Some((alias, &tasks.get(primary)?.origin)));
+    tasks
+        .iter()
+        .map(|(key, task)| (key, &task.origin))
+        .chain(outputs)
+        .map(|(key, origin)| (key.clone(), origin.config().clone()))
+        .collect()
+}
+
+/// Maximum number of the candidates suggested for an unknown task
+const MAX_SUGGESTIONS: usize = 3;
+
+/// Unknown task with the similar tasks suggested
+#[derive(Debug)]
+pub struct TaskNotFound {
+    /// Unknown task
+    key: TaskKey,
+    /// Task depending on the unknown task with where it is required, or None if given as a target
+    parent: Option<(TaskKey, Option<Span>)>,
+    /// Similar tasks with the rusk.toml defining them, the closest first
+    candidates: Vec<(TaskKey, NormarizedPath)>,
+}
+
+impl TaskNotFound {
+    /// Find the tasks whose names are close to the unknown one in edit distance.
+    fn new(
+        key: TaskKey,
+        parent: Option<(TaskKey, Option<Span>)>,
+        defined: &[(TaskKey, NormarizedPath)],
+    ) -> Self {
+        let name = key.as_ref();
+        // Allow about one typo per three characters
+        let threshold = (name.chars().count() / 3).max(1);
+        let candidates = defined
+            .iter()
+            .filter_map(|(candidate, config)| {
+                let distance = strsim::damerau_levenshtein(name, candidate.as_ref());
+                (distance <= threshold).then_some((distance, candidate, config))
             })
-            .collect::<Result<Vec<_>, _>>()?;
-        let graph = TreeNode::new_vec(tasks, tk)?;
-        exec_all(graph).await?;
+            .sorted_by(|(a, a_key, _), (b, b_key, _)| (a, a_key.as_ref()).cmp(&(b, b_key.as_ref())))
+            .take(MAX_SUGGESTIONS)
+            .map(|(_, candidate, config)| (candidate.clone(), config.clone()))
+            .collect();
+        Self {
+            key,
+            parent,
+            candidates,
+        }
+    }
+}
+
+impl Display for TaskNotFound {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        ////////////////////////////////////////////////
+        //
+        // Format:
+        //     Task (task_name) not found, required by (task_name)
+        //       --> (path):(line):(col)
+        //        |
+        //     (line) | (text)
+        //        |   ^^^^
+        //     Did you mean:
+        //       - (task_name)  in (path)
+        //
+        ////////////////////////////////////////////////
+
+        /// Write "in (path)"
+        fn write_config(
+            f: &mut std::fmt::Formatter<'_>,
+            config: &NormarizedPath,
+        ) -> std::fmt::Result {
+            write!(
+                f,
+                "  {} {}",
+                "in".dimmed().italic(),
+                config.as_short_str().yellow().dimmed().italic()
+            )
+        }
+
+        let Self {
+            key,
+            parent,
+            candidates,
+        } = self;
+        write!(f, "Task {key:?} not found")?;
+        if let Some((parent, span)) = parent {
+            write!(f, ", required by {parent}")?;
+            if let Some(span) = span {
+                write!(f, "\n{span}")?;
+            }
+        }
+        if !candidates.is_empty() {
+            write!(f, "\nDid you mean:")?;
+            for (candidate, config) in candidates {
+                write!(f, "\n  - {candidate}")?;
+                write_config(f, config)?;
+            }
+        }
         Ok(())
     }
 }
 
+/// Task requested from the command line
+#[derive(Clone)]
+pub struct Target {
+    /// Task name
+    pub name: String,
+    /// Parameters given as `NAME=VALUE`
+    pub params: Vec<(String, String)>,
+}
+
+impl Target {
+    /// Key of the task, resolved from the current directory
+    fn key(&self) -> Result<TaskKey, TaskKeyParseError> {
+        Ok(TaskKeyRelative::try_from(self.name.clone())?.into_task_key(get_current_dir()))
+    }
+    /// Keys of the tasks, resolved from the current directory
+    fn keys<'a>(
+        targets: impl IntoIterator<Item = &'a Target>,
+    ) -> Result<Vec<TaskKey>, TaskKeyParseError> {
+        targets.into_iter().map(Target::key).collect()
+    }
+}
+
 /// Task configuration
+#[derive(Clone)]
 pub struct Task {
+    /// Where the task is defined
+    pub origin: TaskOrigin,
     /// Environment variables that are specific to this task
     pub envs: HashMap<String, String>,
     /// Script to be executed
@@ -103,12 +814,154 @@ pub struct Task {
     pub cwd: NormarizedPath,
     /// Dependencies
     pub depends: Vec<TaskKey>,
+    /// Files checked for freshness in addition to the file dependencies.
+    /// If given, phony dependencies are only for ordering.
+    pub inputs: Option<Vec<NormarizedPath>>,
+    /// Files produced by the task in addition to the target itself
+    pub outputs: Vec<NormarizedPath>,
+    /// Arguments which the task accepts
+    pub args: Vec<TaskArg>,
+    /// Compare content hashes instead of modified datetimes, overriding the settings
+    pub fingerprint: Option<bool>,
+    /// How to treat the outputs not produced by a successful run, overriding the settings
+    pub verify_outputs: Option<VerifyOutputs>,
+    /// Delete the outputs modified by a failed run, overriding the settings
+    pub delete_on_error: Option<bool>,
+    /// Service configuration if the task is a long-running service
+    pub service: Option<ServiceConfig>,
+}
+
+/// Argument declaration of a task, exposed to the script as an environment variable
+#[derive(Clone)]
+pub struct TaskArg {
+    /// Name of the argument
+    pub name: String,
+    /// Value used when the argument is not given
+    pub default: Option<String>,
+    /// Whether the argument must be given
+    pub required: bool,
+}
+
+/// Template of file tasks like `build/%.html`, where `%` matches any non-empty stem
+pub struct PatternRule {
+    /// Absolute path of the target before `%`
+    prefix: String,
+    /// Target after `%`
+    suffix: String,
+    /// Task to be instantiated, whose file dependencies may contain `%`
+    task: Task,
+    /// Absolute paths of the dependencies, inputs and outputs written with `%`
+    templates: HashSet<NormarizedPath>,
+}
+
+/// Environment variable name to pass the stem of the pattern rule
+const STEM_ENV: &str = "RUSK_STEM";
+
+impl PatternRule {
+    /// Make a pattern rule from the target containing `%`.
+    /// The last `%` is used, since the directory of the config file may contain `%`.
+    pub fn new(target: &NormarizedPath, task: Task, templates: HashSet<NormarizedPath>) -> Self {
+        let (prefix, suffix) = target.as_abs_str().rsplit_once('%').unwrap(); // Checked by the caller
+        Self {
+            prefix: prefix.to_owned(),
+            suffix: suffix.to_owned(),
+            task,
+            templates,
+        }
+    }
+    /// Match the file against the target, returning the stem
+    fn stem<'a>(&self, file: &'a NormarizedPath) -> Option<&'a str> {
+        file.as_abs_str()
+            .strip_prefix(&self.prefix)?
+            .strip_suffix(&self.suffix)
+            .filter(|stem| !stem.is_empty())
+    }
+    /// Target containing `%`
+    fn target(&self) -> TaskKey {
+        let target = format!("{}%{}", self.prefix, self.suffix);
+        TaskKey::File(NormarizedPath::from(Path::new(&target)))
+    }
+    /// Instantiate the task with the stem
+    fn instantiate(&self, stem: &str) -> Task {
+        let substitute = |path: &mut NormarizedPath| {
+            if !self.templates.contains(path) {
+                return;
+            }
+            // The last `%` is the one written, since the directory of the config file comes first
+            if let Some((prefix, suffix)) = path.as_abs_str().rsplit_once('%') {
+                *path = NormarizedPath::from(Path::new(&format!("{prefix}{stem}{suffix}")));
+            }
+        };
+        let mut task = self.task.clone();
+        for dep in &mut task.depends {
+            if let TaskKey::File(path) = dep {
+                substitute(path);
+            }
+        }
+        task.inputs.iter_mut().flatten().for_each(substitute);
+        task.outputs.iter_mut().for_each(substitute);
+        task.envs.insert(STEM_ENV.to_owned(), stem.to_owned());
+        task
+    }
+}
+
+/// Instantiate the pattern rule for the file target without an exact match.
+/// Like Make, the rule with the shortest stem is chosen among those whose file dependencies
+/// exist or can be made by `is_known` tasks. If there is none, an existing file is left as
+/// a plain source, and a missing one takes the rule with the shortest stem to report what is missing.
+fn resolve_rule(
+    rules: &[PatternRule],
+    file: &NormarizedPath,
+    is_known: impl Fn(&TaskKey) -> bool,
+) -> Option<Task> {
+    let candidates = rules
+        .iter()
+        .filter_map(|rule| Some((rule.stem(file)?, rule)))
+        .sorted_by_key(|(stem, _)| stem.len())
+        .map(|(stem, rule)| rule.instantiate(stem))
+        .collect_vec();
+    let applicable = candidates.iter().position(|task| {
+        task.depends.iter().all(|dep| match dep {
+            TaskKey::File(path) => is_known(dep) || path.exists(),
+            TaskKey::Phony(_) => true,
+        })
+    });
+    match applicable {
+        Some(index) => candidates.into_iter().nth(index),
+        None if file.exists() => None,
+        None => candidates.into_iter().next(),
+    }
+}
+
+/// Check if the name can be used as an argument name.
+/// Must match `^[a-zA-Z_][a-zA-Z0-9_]*$` to be usable as an environment variable.
+pub fn is_valid_arg_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    let Some(first) = chars.next() else {
+        return false;
+    };
+    (first.is_ascii_alphabetic() || first == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
 }
 
+/// Environment variable name to pass the arguments after `--`
+const EXTRA_ARGS_ENV: &str = "RUSK_ARGS";
+
 /// Task execution global options
 pub struct ExecuteOpts {
     /// Environment variables
     pub envs: HashMap<String, String>,
+    /// Arguments after `--`, passed to the target tasks
+    pub extra_args: Vec<String>,
+    /// Maximum number of scripts running at once, overriding the settings
+    pub jobs: Option<NonZeroUsize>,
+    /// Keep executing independent tasks after a failure
+    pub keep_going: bool,
+    /// Signal to cancel the running scripts
+    pub kill_signal: KillSignal,
+    /// Services kept running after the execution, managed by the caller.
+    /// If None, the services are stopped when the execution ends, except for the targets which are waited for.
+    pub services: Option<Services>,
     /// IO
     pub io: IOSet,
 }
@@ -117,43 +970,193 @@ impl Default for ExecuteOpts {
     fn default() -> Self {
         Self {
             envs: std::env::vars().collect(),
+            extra_args: Vec::new(),
+            jobs: None,
+            keep_going: false,
+            kill_signal: Default::default(),
+            services: None,
             io: Default::default(),
         }
     }
 }
 
+/// Retain only the tasks reachable from the targets,
+/// instantiating the pattern rules for the file tasks without an exact match
+fn closure(
+    mut tasks: HashMap<TaskKey, Task>,
+    rules: &[PatternRule],
+    aliases: &HashMap<TaskKey, TaskKey>,
+    targets: &[TaskKey],
+) -> HashMap<TaskKey, Task> {
+    let mut reachable: HashMap<TaskKey, Task> = HashMap::new();
+    let mut stack = targets.to_vec();
+    while let Some(key) = stack.pop() {
+        if reachable.contains_key(&key) {
+            continue;
+        }
+        if let Some(primary) = aliases.get(&key) {
+            stack.push(primary.clone());
+            continue;
+        }
+        let task = tasks.remove(&key).or_else(|| {
+            let file = key.as_file()?;
+            resolve_rule(rules, file, |dep| {
+                tasks.contains_key(dep) || reachable.contains_key(dep)
+            })
+        });
+        if let Some(task) = task {
+            stack.extend(task.depends.iter().cloned());
+            // Inputs may be generated by the other tasks as well
+            stack.extend(task.inputs.iter().flatten().cloned().map(TaskKey::File));
+            reachable.insert(key, task);
+        }
+    }
+    reachable
+}
+
+/// Resolve the argument values of a task into environment variables.
+/// `given` is `None` if the task is not a target.
+fn resolve_args(
+    key: &TaskKey,
+    origin: &TaskOrigin,
+    decls: &[TaskArg],
+    given: Option<Vec<(String, String)>>,
+    extra_args: &[String],
+) -> Result<HashMap<String, String>, Diagnostic<TaskParseError>> {
+    let mut values = HashMap::new();
+    if let Some(given) = given {
+        for (name, value) in given {
+            if !decls.iter().any(|arg| arg.name == name) {
+                let hint = if decls.is_empty() {
+                    "the task declares no arguments".to_owned()
+                } else {
+                    format!(
+                        "declared arguments: {}",
+                        decls.iter().map(|arg| &arg.name).join(", ")
+                    )
+                };
+                let err = TaskParseError::UnknownArgument {
+                    key: key.clone(),
+                    name,
+                };
+                return Err(Diagnostic::new(err, origin.locate(&[ARGS])).with_hint(hint));
+            }
+            values.insert(name, value);
+        }
+        if !extra_args.is_empty() {
+            // NOTE: Not quoted, since deno_task_shell splits the expanded variable at whitespace
+            // without regard to quotes. So arguments containing whitespace cannot be passed as they are.
+            values.insert(EXTRA_ARGS_ENV.to_owned(), extra_args.join(" "));
+        }
+    }
+    for (i, arg) in decls.iter().enumerate() {
+        if values.contains_key(&arg.name) {
+            continue;
+        }
+        match &arg.default {
+            Some(default) => {
+                values.insert(arg.name.clone(), default.clone());
+            }
+            None if arg.required => {
+                let err = TaskParseError::MissingArgument {
+                    key: key.clone(),
+                    name: arg.name.clone(),
+                };
+                let span = origin.locate(&[ARGS, Step::Index(i)]);
+                let hint = format!("pass it as `{key} {}=VALUE` or give it a default", arg.name);
+                return Err(Diagnostic::new(err, span).with_hint(hint));
+            }
+            None => {}
+        }
+    }
+    Ok(values)
+}
+
+/// Parse the script of the task line by line
+fn parse_script(key: &TaskKey, task: &Task) -> Result<SequentialList, Diagnostic<TaskParseError>> {
+    let mut items = Vec::new();
+    for (i, line) in task
+        .script
+        .iter()
+        .flat_map(|script| script.lines())
+        .enumerate()
+    {
+        match deno_task_shell::parser::parse(line) {
+            Ok(script) => items.extend(script.items),
+            Err(error) => {
+                let span = task
+                    .origin
+                    .locate(&[Step::Key("script")])
+                    .map(|span| span.line_of_string(i));
+                let key = key.clone();
+                let err = TaskParseError::ScriptParseError { key, error };
+                return Err(Diagnostic::new(err, span));
+            }
+        }
+    }
+    Ok(SequentialList { items })
+}
+
 /// Alternative for `TryInto<HashMap<_, TaskExecutable>>` for `HashMap<_, Task>`
 fn into_executable(
     tasks: HashMap<TaskKey, Task>,
+    aliases: HashMap<TaskKey, TaskKey>,
+    mut params: HashMap<TaskKey, Vec<(String, String)>>,
+    settings: &Settings,
     ExecuteOpts {
         envs: global_env,
+        extra_args,
+        jobs,
+        keep_going: _,
+        kill_signal,
+        services,
         io,
     }: ExecuteOpts,
-) -> Result<HashMap<TaskKey, TaskExecutable>, TaskParseError> {
+    state: Rc<RefCell<StateStore>>,
+) -> Result<HashMap<TaskKey, TaskExecutable>, Diagnostic<TaskParseError>> {
     let mut parsed_tasks: HashMap<TaskKey, TaskExecutable> = HashMap::new();
+    let global_envs = Rc::new(global_env.into_iter().collect());
+    let jobs = jobs
+        .or(settings.jobs)
+        .or_else(|| std::thread::available_parallelism().ok())
+        .map_or(1, NonZeroUsize::get);
+    let jobs = Rc::new(Semaphore::new(jobs));
+    let services = services.unwrap_or_default();
 
     for (key, task) in tasks {
-        let script = {
-            let mut items = Vec::new();
-            if let Some(script) = task.script {
-                for line in script.lines() {
-                    items.extend(match deno_task_shell::parser::parse(line) {
-                        Ok(script) => script.items,
-                        Err(error) => {
-                            return Err(TaskParseError::ScriptParseError { key, error })?;
-                        }
-                    });
-                }
-            };
-            SequentialList { items }
-        };
+        let script = parse_script(&key, &task)?;
 
         let Task {
-            envs, cwd, depends, ..
+            origin,
+            envs,
+            script: source,
+            cwd,
+            mut depends,
+            inputs,
+            outputs,
+            args,
+            fingerprint,
+            verify_outputs,
+            delete_on_error,
+            service,
         } = task;
+        let args = resolve_args(&key, &origin, &args, params.remove(&key), &extra_args)?;
 
         if !cwd.is_dir() {
-            return Err(TaskParseError::DirectoryNotFound(cwd));
+            let span = origin.locate(&[Step::Key("cwd")]);
+            return Err(Diagnostic::new(
+                TaskParseError::DirectoryNotFound(cwd),
+                span,
+            ));
+        }
+
+        // Inputs are waited for like the file dependencies
+        let order_only = inputs.is_some();
+        for input in inputs.into_iter().flatten() {
+            let input = TaskKey::File(input);
+            if !depends.contains(&input) {
+                depends.push(input);
+            }
         }
 
         // If dependency is a file, create a virtual TaskExecutable because it may not be actual Task
@@ -166,37 +1169,427 @@ fn into_executable(
             }
         }
 
+        let outputs = key.as_file().cloned().into_iter().chain(outputs).collect();
+
         parsed_tasks.insert(
             key.clone(),
             TaskExecutableInner {
                 io: io.clone(),
                 key,
+                source,
                 script,
                 depends,
-                envs: global_env.clone().into_iter().chain(envs).collect(),
+                global_envs: Rc::clone(&global_envs),
+                jobs: Rc::clone(&jobs),
+                kill_signal: kill_signal.clone(),
+                fingerprint: fingerprint.or(settings.fingerprint).unwrap_or(false),
+                order_only,
+                service,
+                services: services.clone(),
+                verify_outputs: verify_outputs
+                    .or(settings.verify_outputs)
+                    .unwrap_or_default(),
+                delete_on_error: delete_on_error
+                    .or(settings.delete_on_error)
+                    .unwrap_or(false),
+                outputs,
+                state: Rc::clone(&state),
+                envs: envs.into_iter().chain(args).collect(),
                 cwd,
+                origin,
             }
             .into(),
         );
     }
 
+    // Additional outputs are resolved to the tasks producing them, overriding the virtual File Tasks
+    for (alias, primary) in aliases {
+        if parsed_tasks.contains_key(&primary) {
+            parsed_tasks.insert(alias, TaskExecutable::alias(primary));
+        }
+    }
+
     Ok(parsed_tasks)
 }
 
-async fn exec_all(roots: impl IntoIterator<Item = TaskTree>) -> TaskResult {
-    async fn exec_node(node: &TaskTree) -> TaskResult {
-        let child_futures = node.children.iter().map(|child| exec_node(child));
-        try_join_all(child_futures).await?;
-        node.item.as_future().await
+async fn exec_all(
+    roots: Vec<TaskTree>,
+    keep_going: bool,
+    kill_signal: KillSignal,
+) -> Result<(), RuskError> {
+    async fn exec_node(node: &TaskTree, canceller: &Canceller) -> TaskResult {
+        let child_futures = node
+            .children
+            .iter()
+            .map(|child| exec_node(child, canceller));
+        // Let all the children finish, then skip this task if any of them failed
+        join_all(child_futures)
+            .await
+            .into_iter()
+            .collect::<TaskResult>()?;
+        let res = node.item.as_future().await;
+        if res.is_err() && !canceller.keep_going {
+            canceller.cancel();
+        }
+        res
+    }
+
+    let canceller = Canceller {
+        signal: kill_signal,
+        keep_going,
+    };
+    let futures = join_all(roots.iter().map(|root| exec_node(root, &canceller)));
+    tokio::select! {
+        _ = futures => {}
+        _ = canceller.watch() => unreachable!(),
+    }
+
+    // Collect the results of all the tasks
+    let mut failures = Vec::new();
+    let mut interrupted = Vec::new();
+    let mut skipped = Vec::new();
+    for node in TreeNode::post_order(&roots) {
+        match &node.item.0.try_borrow().unwrap() as &TaskExecutableState {
+            TaskExecutableState::Done(Err(TaskError::Interrupted { key })) => {
+                interrupted.push(key.clone())
+            }
+            TaskExecutableState::Done(Err(TaskError::Cancelled { key })) => {
+                skipped.push(key.clone())
+            }
+            TaskExecutableState::Done(Err(err)) => failures.push(err.clone()),
+            TaskExecutableState::Initialized(_) => skipped.push(node.key.clone()),
+            _ => {}
+        }
+    }
+    if failures.is_empty() && canceller.is_cancelled() {
+        return Err(RuskError::Interrupted(interrupted));
     }
+    match failures.len() {
+        0 => Ok(()),
+        1 if interrupted.is_empty() && skipped.is_empty() => {
+            Err(RuskError::from(failures.pop().unwrap()))
+        }
+        _ => Err(RuskError::TasksFailed {
+            failures,
+            interrupted,
+            skipped,
+        }),
+    }
+}
 
-    let futures = roots
+/// Wait for the services given as targets until all of them exit or Ctrl-C is pressed
+async fn wait_services(services: &Services, targets: Vec<TaskKey>) -> Result<(), RuskError> {
+    let mut running = targets
         .into_iter()
-        .map(|root| async move { exec_node(&root).await });
-    try_join_all(futures).await?;
+        .filter(|key| services.contains(key))
+        .collect_vec();
+    while !running.is_empty() {
+        tokio::select! {
+            (key, exit_code) = services.wait_any(&running) => {
+                running.retain(|running| *running != key);
+                if exit_code != 0 {
+                    return Err(RuskError::from(TaskError::Execution { key, exit_code }));
+                }
+            }
+            _ = tokio::signal::ctrl_c() => return Err(RuskError::Interrupted(running)),
+        }
+    }
     Ok(())
 }
 
+/// Grace period between SIGTERM and SIGKILL on cancellation
+const CANCEL_GRACE_PERIOD: Duration = Duration::from_secs(5);
+
+/// Cancellation of the running scripts, shared by all the tasks
+struct Canceller {
+    /// Signal sent to the scripts of all the tasks
+    signal: KillSignal,
+    /// Keep executing independent tasks after a failure
+    keep_going: bool,
+}
+
+impl Canceller {
+    /// Send SIGTERM to the running scripts and prevent new scripts from starting
+    fn cancel(&self) {
+        if !self.is_cancelled() {
+            self.signal.send(SignalKind::SIGTERM);
+        }
+    }
+    /// Whether the tasks have been cancelled
+    fn is_cancelled(&self) -> bool {
+        self.signal.aborted_code().is_some()
+    }
+    /// Cancel on Ctrl-C, then send SIGKILL after the grace period or on a second Ctrl-C.
+    /// This never returns, so it must be raced with the tasks.
+    async fn watch(&self) -> std::convert::Infallible {
+        if !self.is_cancelled() {
+            tokio::select! {
+                _ = tokio::signal::ctrl_c() => self.cancel(),
+                _ = self.signal.wait_aborted() => {}
+            }
+        }
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {}
+            _ = tokio::time::sleep(CANCEL_GRACE_PERIOD) => {}
+        }
+        self.signal.send(SignalKind::SIGKILL);
+        std::future::pending().await
+    }
+}
+
+/// Make the plan of all the nodes in the order of execution
+async fn plan_all(roots: &[TaskTree]) -> Vec<PlanEntry> {
+    let order = TreeNode::post_order(roots);
+    let mut pending = HashSet::new();
+    let mut failed = HashSet::new();
+    let mut entries = Vec::new();
+    for node in order {
+        let key = node.key.clone();
+        // The graph is only for planning, so the state can be taken out.
+        let state = std::mem::replace(
+            &mut node.item.0.try_borrow_mut().unwrap() as &mut TaskExecutableState,
+            TaskExecutableState::Done(Ok(())),
+        );
+        if let TaskExecutableState::Alias(primary) = &state {
+            // Not listed, but going to be updated or to fail together with the primary task
+            if failed.contains(primary) {
+                failed.insert(key);
+            } else if pending.contains(primary) {
+                pending.insert(key);
+            }
+            continue;
+        }
+        let TaskExecutableState::Initialized(inner) = state else {
+            let exists = match &node.key {
+                TaskKey::File(file) => matches!(tokio::fs::try_exists(file).await, Ok(true)),
+                TaskKey::Phony(_) => false,
+            };
+            entries.push(PlanEntry {
+                key,
+                action: PlanAction::Source { exists },
+                detail: None,
+            });
+            continue;
+        };
+        // Like the execution, the dependents of a failed task are skipped without being checked
+        let blocker = node
+            .children
+            .iter()
+            .find(|child| failed.contains(&child.key));
+        let action = match blocker {
+            Some(dep) => {
+                failed.insert(key.clone());
+                PlanAction::Blocked(dep.key.clone())
+            }
+            None => match inner.check(&pending).await {
+                Ok((Freshness::Outdated(reason), _)) => {
+                    pending.insert(key.clone());
+                    PlanAction::Run(reason)
+                }
+                Ok((Freshness::UpToDate, _)) => PlanAction::Skip,
+                Err(err) => {
+                    failed.insert(key.clone());
+                    PlanAction::Fail(err)
+                }
+            },
+        };
+        let TaskExecutableInner {
+            source, cwd, envs, ..
+        } = *inner;
+        entries.push(PlanEntry {
+            key,
+            action,
+            detail: Some(PlanDetail {
+                source,
+                cwd,
+                envs: envs.into_iter().sorted().collect(),
+            }),
+        });
+    }
+    entries
+}
+
+/// Result of the up-to-date check
+enum Freshness {
+    /// The script needs to be executed
+    Outdated(OutdatedReason),
+    /// The target is up to date, so the script is skipped
+    UpToDate,
+}
+
+/// Reason why the script is executed
+#[derive(Debug, Clone)]
+pub enum OutdatedReason {
+    /// Phony task is always executed
+    Phony,
+    /// Service is started unless it is running with the same script and dependencies
+    Service,
+    /// File task depending on a phony task is always executed, unless the inputs are given explicitly
+    PhonyDependency(TaskKey),
+    /// Output file does not exist
+    TargetMissing(NormarizedPath),
+    /// Dependency file is newer than the target, or is going to be updated
+    DependencyUpdated(NormarizedPath),
+    /// No successful run is recorded in fingerprint mode
+    NotRecorded,
+    /// Dependency file is added, removed, or its content differs from the last successful run
+    InputChanged(NormarizedPath),
+    /// Script differs from the last successful run
+    ScriptChanged,
+    /// Environment variables differ from the last successful run
+    EnvsChanged,
+}
+
+/// Compare the record with the one of the last successful run, returning the reason if they differ.
+/// Without the previous record, the task is regarded as outdated only in fingerprint mode.
+fn compare_record(
+    prev: Option<&TaskRecord>,
+    current: &TaskRecord,
+    fingerprint_mode: bool,
+) -> Option<OutdatedReason> {
+    let Some(prev) = prev else {
+        return fingerprint_mode.then_some(OutdatedReason::NotRecorded);
+    };
+    // Both of the input lists are sorted
+    let changed_input = current
+        .inputs
+        .iter()
+        .merge_join_by(&prev.inputs, |a, b| a.cmp(b))
+        .find_map(|pair| match pair {
+            EitherOrBoth::Left(path) | EitherOrBoth::Right(path) => Some(path),
+            EitherOrBoth::Both(..) => None,
+        })
+        .or_else(|| {
+            current
+                .hashes
+                .iter()
+                .find(|(path, hash)| prev.hashes.get(*path) != Some(hash))
+                .map(|(path, _)| path)
+        });
+    if let Some(input) = changed_input {
+        return Some(OutdatedReason::InputChanged(NormarizedPath::from(
+            Path::new(input),
+        )));
+    }
+    if prev.script != current.script {
+        return Some(OutdatedReason::ScriptChanged);
+    }
+    if prev.envs != current.envs {
+        return Some(OutdatedReason::EnvsChanged);
+    }
+    None
+}
+
+impl Display for OutdatedReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OutdatedReason::Phony => write!(f, "phony task"),
+            OutdatedReason::Service => write!(f, "service"),
+            OutdatedReason::PhonyDependency(dep) => write!(f, "depends on phony task {dep}"),
+            OutdatedReason::TargetMissing(output) => write!(f, "{output} not found"),
+            OutdatedReason::DependencyUpdated(dep) => write!(f, "{dep} is newer"),
+            OutdatedReason::NotRecorded => write!(f, "no successful run recorded"),
+            OutdatedReason::InputChanged(dep) => write!(f, "{dep} has changed"),
+            OutdatedReason::ScriptChanged => write!(f, "script has changed"),
+            OutdatedReason::EnvsChanged => write!(f, "environment variables have changed"),
+        }
+    }
+}
+
+/// Entry of the execution plan
+pub struct PlanEntry {
+    /// TaskKey
+    pub key: TaskKey,
+    /// What happens to the task
+    pub action: PlanAction,
+    /// Task details. None if the entry is a file without a task
+    pub detail: Option<PlanDetail>,
+}
+
+/// What happens to the task in the plan
+pub enum PlanAction {
+    /// The script is executed
+    Run(OutdatedReason),
+    /// The target is up to date
+    Skip,
+    /// File without a task
+    Source { exists: bool },
+    /// The task fails before executing the script
+    Fail(TaskError),
+    /// The task is skipped, since the dependency fails
+    Blocked(TaskKey),
+}
+
+/// Task details shown in the plan
+pub struct PlanDetail {
+    /// Script source
+    pub source: Option<String>,
+    /// Working directory
+    pub cwd: NormarizedPath,
+    /// Environment variables added to the global ones
+    pub envs: Vec<(String, String)>,
+}
+
+impl Display for PlanEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        ////////////////////////////////////////////////
+        //
+        // Format:
+        //     (action) (task_name)  (reason)
+        //         cwd  (cwd)
+        //         env  (name)=(value)
+        //         $ (script line)
+        //
+        ////////////////////////////////////////////////
+
+        let PlanEntry {
+            key,
+            action,
+            detail,
+        } = self;
+        match action {
+            PlanAction::Run(reason) => {
+                write!(f, "{} {key}  {reason}", "run ".green().bold())?;
+            }
+            PlanAction::Skip => {
+                write!(f, "{} {key}  {}", "skip".dimmed(), "up to date".dimmed())?;
+            }
+            PlanAction::Source { exists: true } => {
+                write!(f, "{} {key}", "src ".dimmed())?;
+            }
+            PlanAction::Source { exists: false } => {
+                write!(f, "{} {key}  {}", "src ".red(), "file not found".red())?;
+            }
+            PlanAction::Fail(err) => {
+                write!(
+                    f,
+                    "{} {key}  {}",
+                    "fail".red().bold(),
+                    err.to_string().red()
+                )?;
+            }
+            PlanAction::Blocked(dep) => {
+                write!(
+                    f,
+                    "{} {key}  {}",
+                    "skip".red(),
+                    format_args!("{dep} fails").to_string().red()
+                )?;
+            }
+        }
+        if let (PlanAction::Run(_), Some(detail)) = (action, detail) {
+            write!(f, "\n    {}  {}", "cwd".dimmed(), detail.cwd)?;
+            for (name, value) in &detail.envs {
+                write!(f, "\n    {}  {name}={value}", "env".dimmed())?;
+            }
+            for line in detail.source.iter().flat_map(|source| source.lines()) {
+                write!(f, "\n    {} {line}", "$".dimmed())?;
+            }
+        }
+        Ok(())
+    }
+}
+
 /// Independent TaskExecutable with state
 struct TaskExecutable(RefCell<TaskExecutableState>);
 
@@ -205,11 +1598,17 @@ impl TaskExecutable {
     fn empty() -> Self {
         TaskExecutable(RefCell::new(TaskExecutableState::Done(Ok(()))))
     }
+    /// Create a TaskExecutable which represents an additional output of the primary task
+    fn alias(primary: TaskKey) -> Self {
+        TaskExecutable(RefCell::new(TaskExecutableState::Alias(primary)))
+    }
     pub async fn as_future(&self) -> TaskResult {
         let res = 'res: {
             'early_return: {
                 let mut rx = match &self.0.try_borrow().unwrap() as &TaskExecutableState {
                     TaskExecutableState::Done(result) => return result.clone(),
+                    // The primary task is a child, so it has already been done successfully
+                    TaskExecutableState::Alias(_) => return Ok(()),
                     TaskExecutableState::Processing(rx) => {
                         if let Some(res) = rx.borrow().as_ref() {
                             break 'res res.clone();
@@ -245,98 +1644,395 @@ impl TaskExecutable {
 }
 
 impl TaskExecutableInner {
-    pub async fn into_future(self) -> TaskResult {
+    /// Error of the dependency file not found, located in the rusk.toml
+    fn dependency_not_found(&self, dep: &TaskKey, file: &NormarizedPath) -> TaskError {
+        let span = self.origin.locate_dependency(dep);
+        TaskError::dependency_not_found(&self.key, file, span)
+    }
+    /// Check whether the script needs to be executed.
+    /// Dependencies in `pending` are regarded as going to be updated before this task.
+    /// The record of the task is also returned if computed, to be stored after the run.
+    async fn check(
+        &self,
+        pending: &HashSet<TaskKey>,
+    ) -> Result<(Freshness, Option<TaskRecord>), TaskError> {
         let TaskExecutableInner {
-            io,
             key,
-            envs,
-            script,
-            cwd,
             depends,
+            outputs,
+            fingerprint: fingerprint_mode,
+            order_only,
+            service,
+            ..
         } = self;
 
-        'check_file: {
-            match &key {
-                TaskKey::File(file) => {
-                    // Step 1: Collect dependency file Metadata Objects.
-                    // If File not found, the task won't be executed. So check at this point
-                    let mut dep_file_metadatas = Vec::new();
-                    let dep_count = depends.len();
-                    for dep in depends {
-                        if let TaskKey::File(dep_file) = dep {
-                            let Ok(metadata) = tokio::fs::metadata(&dep_file).await else {
-                                return Err(TaskError::DependencyFileNotFound {
-                                    dep_file,
-                                    task: key,
-                                });
-                            };
-                            dep_file_metadatas.push(metadata);
-                        }
-                    }
-                    if dep_count != dep_file_metadatas.len() {
-                        // NOTE: If PhonyTask is included, the script is always executed.
-                        break 'check_file;
+        if outputs.is_empty() {
+            // Check only the existence of the dependency file
+            for dep in depends {
+                if let TaskKey::File(file) = dep {
+                    if !pending.contains(dep)
+                        && !matches!(tokio::fs::try_exists(file).await, Ok(true))
+                    {
+                        return Err(self.dependency_not_found(dep, file));
                     }
+                }
+            }
+            let reason = match service {
+                Some(_) => OutdatedReason::Service,
+                None => OutdatedReason::Phony,
+            };
+            return Ok((Freshness::Outdated(reason), None));
+        }
 
-                    // Step 2: Get the metadata of the file.
-                    // If file not found, it need not to check the modified datetime
-                    let Ok(metadata) = tokio::fs::metadata(file).await else {
-                        break 'check_file;
-                    };
-                    let Ok(modified) = metadata.modified() else {
-                        return Err(TaskError::FailedToGetFileMetadata);
+        // Step 1: Collect dependency file Metadata Objects.
+        // If File not found, the task won't be executed. So check at this point
+        let mut dep_file_metadatas = Vec::new();
+        let mut phony_dep = None;
+        let mut pending_dep = None;
+        for dep in depends {
+            match dep {
+                TaskKey::File(dep_file) if pending.contains(dep) => {
+                    pending_dep.get_or_insert(dep_file);
+                }
+                TaskKey::File(dep_file) => {
+                    let Ok(metadata) = tokio::fs::metadata(dep_file).await else {
+                        return Err(self.dependency_not_found(dep, dep_file));
                     };
+                    dep_file_metadatas.push((dep_file, metadata));
+                }
+                TaskKey::Phony(_) if !order_only => {
+                    phony_dep.get_or_insert(dep);
+                }
+                TaskKey::Phony(_) => {}
+            }
+        }
+        if let Some(dep) = phony_dep {
+            // NOTE: If PhonyTask is included, the script is always executed.
+            return Ok((
+                Freshness::Outdated(OutdatedReason::PhonyDependency(dep.clone())),
+                None,
+            ));
+        }
+        if let Some(dep_file) = pending_dep {
+            return Ok((
+                Freshness::Outdated(OutdatedReason::DependencyUpdated(dep_file.clone())),
+                None,
+            ));
+        }
 
-                    for dep in dep_file_metadatas {
-                        let dep_modified = dep.modified().unwrap(); // Checked above
-                        if modified <= dep_modified {
-                            // Execution is required if the dependency file has been updated
-                            break 'check_file;
-                        }
-                    }
+        if *fingerprint_mode {
+            // Compare the content hashes with the last successful run instead of the modified datetimes
+            let record = self.record().await?;
+            let mut freshness = None;
+            for output in outputs {
+                if !matches!(tokio::fs::try_exists(output).await, Ok(true)) {
+                    freshness = Some(Freshness::Outdated(OutdatedReason::TargetMissing(
+                        output.clone(),
+                    )));
+                    break;
+                }
+            }
+            let freshness = freshness.unwrap_or_else(|| {
+                match compare_record(self.state.borrow().get(key), &record, true) {
+                    Some(reason) => Freshness::Outdated(reason),
+                    None => Freshness::UpToDate,
+                }
+            });
+            return Ok((freshness, Some(record)));
+        }
 
-                    // If none have been updated
-                    return Ok(());
+        // Step 2: Get the oldest modified datetime of the outputs.
+        // If any of them is not found, it need not to check the modified datetime
+        let mut oldest = None;
+        for output in outputs {
+            let Ok(metadata) = tokio::fs::metadata(output).await else {
+                return Ok((
+                    Freshness::Outdated(OutdatedReason::TargetMissing(output.clone())),
+                    None,
+                ));
+            };
+            let Ok(modified) = metadata.modified() else {
+                return Err(TaskError::FailedToGetFileMetadata);
+            };
+            oldest = Some(oldest.map_or(modified, |oldest: SystemTime| oldest.min(modified)));
+        }
+        let oldest = oldest.unwrap(); // outputs is not empty
+
+        for (dep_file, dep) in dep_file_metadatas {
+            let dep_modified = dep.modified().unwrap(); // Checked above
+            if oldest <= dep_modified {
+                // Execution is required if the dependency file has been updated
+                return Ok((
+                    Freshness::Outdated(OutdatedReason::DependencyUpdated(dep_file.clone())),
+                    None,
+                ));
+            }
+        }
+
+        // Step 3: Even if none have been updated, the task itself may have been changed
+        // since the last successful run.
+        let record = self.record().await?;
+        let freshness = match compare_record(self.state.borrow().get(key), &record, false) {
+            Some(reason) => Freshness::Outdated(reason),
+            None => Freshness::UpToDate,
+        };
+        Ok((freshness, Some(record)))
+    }
+
+    /// Make the record of the task to be stored after a successful run.
+    /// The dependency files are hashed only in fingerprint mode.
+    async fn record(&self) -> Result<TaskRecord, TaskError> {
+        let inputs = self.depends.iter().filter_map(TaskKey::as_file);
+        TaskRecord::new(
+            inputs,
+            self.source.as_deref(),
+            &self.envs,
+            &self.outputs,
+            self.fingerprint,
+        )
+        .await
+        .map_err(|err| TaskError::FailedToHashFile {
+            task: self.key.clone(),
+            error: Rc::new(err),
+        })
+    }
+
+    /// Start the service, returning once it gets ready.
+    /// The service already running with the same stamp is kept as it is, otherwise restarted.
+    async fn start_service(self, config: ServiceConfig) -> TaskResult {
+        let record = self.record().await?;
+        let mut modified_list = Vec::new();
+        for input in &record.inputs {
+            modified_list.push(modified(Path::new(input)).await);
+        }
+        let stamp = Stamp {
+            record,
+            modified: modified_list,
+        };
+
+        let TaskExecutableInner {
+            io,
+            key,
+            global_envs,
+            envs,
+            script,
+            cwd,
+            kill_signal,
+            services,
+            ..
+        } = self;
+        if services.is_running(&key, &stamp) {
+            return Ok(());
+        }
+        services.stop(&key).await;
+        if kill_signal.aborted_code().is_some() {
+            return Err(TaskError::Cancelled { key });
+        }
+
+        let (ready_tx, mut ready_rx) = tokio::sync::mpsc::unbounded_channel();
+        let (stdout, stderr) = match &config.ready {
+            Some(ReadyProbe::Log(pattern)) => {
+                let (stdout_reader, stdout) = deno_task_shell::pipe();
+                let (stderr_reader, stderr) = deno_task_shell::pipe();
+                scan_output(stdout_reader, io.stdout, pattern.clone(), ready_tx.clone());
+                scan_output(stderr_reader, io.stderr, pattern.clone(), ready_tx);
+                (stdout, stderr)
+            }
+            _ => (io.stdout, io.stderr),
+        };
+        let envs = global_envs
+            .iter()
+            .chain(&envs)
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+        // NOTE: A service is not counted as a job, since it would occupy the slot forever.
+        let service_signal = kill_signal.child_signal();
+        let mut handle = tokio::task::spawn_local(deno_task_shell::execute_with_pipes(
+            script,
+            ShellState::new(envs, &cwd, Default::default(), service_signal.clone()),
+            io.stdin,
+            stdout,
+            stderr,
+        ));
+        let ready = async {
+            if let Some(probe) = &config.ready {
+                probe.wait(&mut ready_rx).await;
+            }
+        };
+        tokio::select! {
+            _ = ready => {}
+            res = &mut handle => {
+                if kill_signal.aborted_code().is_some() {
+                    return Err(TaskError::Interrupted { key });
                 }
-                TaskKey::Phony(_) => {
-                    // Check only the existence of the dependency file
-                    for dep in depends {
-                        if let TaskKey::File(file) = dep {
-                            if !matches!(tokio::fs::try_exists(&file).await, Ok(true)) {
-                                return Err(TaskError::DependencyFileNotFound {
-                                    dep_file: file,
-                                    task: key,
-                                });
-                            }
-                        }
-                    }
+                return Err(TaskError::ServiceExited {
+                    key,
+                    exit_code: res.unwrap_or(1),
+                });
+            }
+            _ = tokio::time::sleep(config.timeout) => {
+                services.insert(key.clone(), service_signal, handle, stamp);
+                services.stop(&key).await;
+                return Err(TaskError::ServiceNotReady {
+                    key,
+                    timeout: config.timeout,
+                });
+            }
+        }
+        services.insert(key, service_signal, handle, stamp);
+        Ok(())
+    }
+
+    pub async fn into_future(self) -> TaskResult {
+        let (freshness, record) = self.check(&HashSet::new()).await?;
+        if let Freshness::UpToDate = freshness {
+            // Without any record, the current state is taken as the baseline to notice later changes
+            if let Some(record) = record {
+                let mut state = self.state.borrow_mut();
+                if state.get(&self.key).is_none() {
+                    state.record(&self.key, record);
                 }
             }
+            return Ok(());
+        }
+        if let Some(config) = self.service.clone() {
+            return self.start_service(config).await;
+        }
+        let record = match record {
+            Some(record) => record,
+            None => self.record().await?,
+        };
+
+        let TaskExecutableInner {
+            io,
+            key,
+            global_envs,
+            envs,
+            script,
+            cwd,
+            jobs,
+            kill_signal,
+            state,
+            depends,
+            outputs,
+            fingerprint,
+            verify_outputs,
+            delete_on_error,
+            ..
+        } = self;
+
+        // Waiting for dependencies does not occupy a job slot, only running the script does.
+        let _permit = jobs.acquire().await.unwrap(); // The semaphore is never closed
+        if kill_signal.aborted_code().is_some() {
+            return Err(TaskError::Cancelled { key });
+        }
+
+        // Remember the outputs before the run to find the ones modified by the failed script
+        let mut snapshot = Vec::new();
+        if delete_on_error {
+            for output in &outputs {
+                snapshot.push((output, modified(output).await));
+            }
         }
+
+        let envs = global_envs
+            .iter()
+            .chain(&envs)
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
         let exit_code = deno_task_shell::execute_with_pipes(
             script,
-            ShellState::new(envs, &cwd, Default::default(), Default::default()),
+            // NOTE: deno_task_shell sends SIGTERM to its own signal when the script fails,
+            // so a child signal is given not to propagate it to the other tasks.
+            ShellState::new(envs, &cwd, Default::default(), kill_signal.child_signal()),
             io.stdin,
             io.stdout,
             io.stderr,
         )
         .await;
         if exit_code == 0 {
-            Ok(())
+            // Modified datetimes are not compared in fingerprint mode, where only the contents matter
+            let verified = match verify_outputs {
+                VerifyOutputs::Off => Ok(()),
+                _ => verify(&key, &outputs, &depends, !fingerprint).await,
+            };
+            match verified {
+                Err(err) if verify_outputs == VerifyOutputs::Error => return Err(err),
+                Err(err) => eprintln!("{}: {err}", "warning".on_yellow().black().bold()),
+                Ok(()) => {}
+            }
+            state.borrow_mut().record(&key, record);
+            return Ok(());
+        }
+        for (output, before) in snapshot {
+            let after = modified(output).await;
+            if after.is_some() && after != before && tokio::fs::remove_file(output).await.is_ok() {
+                eprintln!(
+                    "{}: Deleted {output} left by the failed task {key:?}",
+                    "warning".on_yellow().black().bold()
+                );
+            }
+        }
+        if kill_signal.aborted_code().is_some() {
+            Err(TaskError::Interrupted { key })
         } else {
             Err(TaskError::Execution { key, exit_code })
         }
     }
 }
 
+/// Get the modified datetime of the file, or None if it does not exist
+async fn modified(path: &Path) -> Option<SystemTime> {
+    tokio::fs::metadata(path).await.ok()?.modified().ok()
+}
+
+/// Check that the outputs exist and, if `check_mtime` is set, are not older than the dependency files
+async fn verify(
+    key: &TaskKey,
+    outputs: &[NormarizedPath],
+    depends: &[TaskKey],
+    check_mtime: bool,
+) -> TaskResult {
+    let mut newest_input = None;
+    if check_mtime {
+        for dep in depends {
+            if let TaskKey::File(dep_file) = dep {
+                let modified = tokio::fs::metadata(dep_file)
+                    .await
+                    .and_then(|metadata| metadata.modified());
+                newest_input = newest_input.max(modified.ok());
+            }
+        }
+    }
+    for output in outputs {
+        let Ok(metadata) = tokio::fs::metadata(output).await else {
+            return Err(TaskError::OutputMissing {
+                key: key.clone(),
+                output: output.clone(),
+            });
+        };
+        if let (Some(newest_input), Ok(modified)) = (newest_input, metadata.modified()) {
+            if modified < newest_input {
+                return Err(TaskError::OutputNotUpdated {
+                    key: key.clone(),
+                    output: output.clone(),
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
 /// TaskExecutable state
 enum TaskExecutableState {
     /// Task is not executed yet
-    Initialized(TaskExecutableInner),
+    Initialized(Box<TaskExecutableInner>),
     /// Task is being executed
     Processing(Receiver<Option<TaskResult>>),
     /// Task is done
     Done(TaskResult),
+    /// Additional output of the primary task, which is done when the primary task is done
+    Alias(TaskKey),
 }
 
 /// TaskExecutable inner data to exec deno_task_shell
@@ -345,19 +2041,47 @@ struct TaskExecutableInner {
     io: IOSet,
     /// TaskKey
     key: TaskKey,
-    /// Environment variables
-    envs: std::collections::HashMap<String, String>,
+    /// Environment variables inherited from the global options
+    global_envs: Rc<HashMap<String, String>>,
+    /// Environment variables specific to this task, including arguments
+    envs: HashMap<String, String>,
+    /// Script source
+    source: Option<String>,
     /// Script to be executed
     script: SequentialList,
     /// Working directory
     cwd: NormarizedPath,
+    /// Semaphore limiting the number of scripts running at once
+    jobs: Rc<Semaphore>,
+    /// Signal to cancel the scripts of all the tasks
+    kill_signal: KillSignal,
+    /// Compare content hashes instead of modified datetimes
+    fingerprint: bool,
+    /// Phony dependencies are only for ordering, not making the task outdated
+    order_only: bool,
+    /// Service configuration if the task is a service
+    service: Option<ServiceConfig>,
+    /// Services shared by all the tasks
+    services: Services,
+    /// How to treat the outputs not produced by a successful run
+    verify_outputs: VerifyOutputs,
+    /// Delete the outputs modified by a failed run
+    delete_on_error: bool,
+    /// Files produced by the task, including the target itself. Empty for phony tasks.
+    outputs: Vec<NormarizedPath>,
+    /// State shared by all the tasks
+    state: Rc<RefCell<StateStore>>,
     /// TaskKeys that this task depends on
     depends: Vec<TaskKey>, // 依存関係の検索についてはTaskKeyを用いるか検討が必要
+    /// Where the task is defined
+    origin: TaskOrigin,
 }
 
 impl From<TaskExecutableInner> for TaskExecutable {
     fn from(val: TaskExecutableInner) -> Self {
-        TaskExecutable(RefCell::new(TaskExecutableState::Initialized(val)))
+        TaskExecutable(RefCell::new(TaskExecutableState::Initialized(Box::new(
+            val,
+        ))))
     }
 }
 
@@ -365,6 +2089,7 @@ impl DigraphItem<TaskKey> for TaskExecutable {
     fn children(&self) -> impl Deref<Target = [TaskKey]> {
         Ref::map::<[TaskKey], _>(self.0.borrow(), |state| match state {
             TaskExecutableState::Initialized(inner) => inner.depends.as_slice(),
+            TaskExecutableState::Alias(primary) => std::slice::from_ref(primary),
             // In case of Done or Processing, there is no additional dependency
             _ => &[],
         })
@@ -378,8 +2103,14 @@ pub enum TaskParseError {
     #[error("Directory not found: {0}")]
     DirectoryNotFound(NormarizedPath),
     /// Task script parse error
-    #[error("Task {key:?} script parse error: {error:?}")]
+    #[error("Task {key:?} script parse error: {error:#}")]
     ScriptParseError { key: TaskKey, error: anyhow::Error },
+    /// Argument not declared in the task
+    #[error("Task {key:?} does not accept argument {name:?}")]
+    UnknownArgument { key: TaskKey, name: String },
+    /// Required argument not given
+    #[error("Task {key:?} requires argument {name:?}")]
+    MissingArgument { key: TaskKey, name: String },
 }
 
 #[derive(Debug, Clone, thiserror::Error)]
@@ -388,11 +2119,55 @@ pub enum TaskError {
     Execution { key: TaskKey, exit_code: i32 },
     #[error("Not supported platform to get file metadata")]
     FailedToGetFileMetadata,
-    #[error("Dependency file {dep_file} not found which is required for {task:?} execution")]
-    DependencyFileNotFound {
-        dep_file: NormarizedPath,
+    #[error(transparent)]
+    DependencyFileNotFound(Diagnostic<DependencyNotFound>),
+    #[error("Failed to hash dependency files of {task:?}: {error}")]
+    FailedToHashFile {
         task: TaskKey,
+        error: Rc<std::io::Error>,
+    },
+    #[error("Task {key:?} succeeded but did not produce {output}")]
+    OutputMissing {
+        key: TaskKey,
+        output: NormarizedPath,
+    },
+    #[error("Task {key:?} succeeded but {output} is still older than its dependencies")]
+    OutputNotUpdated {
+        key: TaskKey,
+        output: NormarizedPath,
     },
+    #[error("Service {key:?} exited with code {exit_code} before getting ready")]
+    ServiceExited { key: TaskKey, exit_code: i32 },
+    #[error("Service {key:?} did not get ready in {timeout:?}")]
+    ServiceNotReady { key: TaskKey, timeout: Duration },
+    #[error("Task {key:?} was interrupted")]
+    Interrupted { key: TaskKey },
+    #[error("Task {key:?} was cancelled before starting")]
+    Cancelled { key: TaskKey },
+}
+
+impl TaskError {
+    /// Dependency file of the task not found, located at the entry listing it
+    fn dependency_not_found(task: &TaskKey, dep_file: &NormarizedPath, span: Option<Span>) -> Self {
+        let err = DependencyNotFound {
+            dep_file: dep_file.clone(),
+            task: task.clone(),
+        };
+        let hint = if crate::fs::is_glob(dep_file.as_short_str()) {
+            "the glob pattern matches no file"
+        } else {
+            "create the file, or define a task producing it"
+        };
+        Self::DependencyFileNotFound(Diagnostic::new(err, span).with_hint(hint))
+    }
+}
+
+/// Dependency file which neither exists nor is produced by any task
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("Dependency file {dep_file} not found which is required for {task:?} execution")]
+pub struct DependencyNotFound {
+    dep_file: NormarizedPath,
+    task: TaskKey,
 }
 
 /// Task result alias
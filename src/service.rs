@@ -0,0 +1,208 @@
+//! Long-running service tasks, kept alive while the other tasks run.
+
+use std::{
+    cell::RefCell,
+    io::{BufRead, BufReader, Read},
+    rc::Rc,
+    time::{Duration, SystemTime},
+};
+
+use deno_task_shell::{KillSignal, ShellPipeReader, ShellPipeWriter, SignalKind};
+use hashbrown::{HashMap, HashSet};
+use regex::Regex;
+use tokio::{sync::mpsc, task::JoinHandle};
+
+use crate::{path::NormarizedPath, state::TaskRecord, taskkey::TaskKey};
+
+/// Grace period between SIGTERM and SIGKILL when stopping a service
+const STOP_GRACE_PERIOD: Duration = Duration::from_secs(5);
+
+/// Interval of polling the port or the file of the readiness probe
+const PROBE_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Default time to wait for a service to get ready
+pub const DEFAULT_READY_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Service configuration of a task
+#[derive(Clone)]
+pub struct ServiceConfig {
+    /// Condition for the service to be regarded as ready. Ready as soon as started if None.
+    pub ready: Option<ReadyProbe>,
+    /// Time to wait for the service to get ready
+    pub timeout: Duration,
+}
+
+/// Condition for a service to be regarded as ready
+#[derive(Clone)]
+pub enum ReadyProbe {
+    /// TCP port on localhost accepts connections
+    Port(u16),
+    /// Line matching the pattern is printed to stdout or stderr
+    Log(Regex),
+    /// File exists
+    File(NormarizedPath),
+}
+
+/// What a service was started from, to restart it when changed
+#[derive(PartialEq, Eq)]
+pub struct Stamp {
+    /// Record of the task, including the hashes of the script and the environment variables
+    pub record: TaskRecord,
+    /// Modified datetimes of the dependency files
+    pub modified: Vec<Option<SystemTime>>,
+}
+
+/// Running service
+struct Service {
+    /// Signal to stop the service
+    kill_signal: KillSignal,
+    /// Handle of the running script, resolving to the exit code
+    handle: JoinHandle<i32>,
+    /// What the service was started from
+    stamp: Stamp,
+}
+
+impl Service {
+    /// Stop the service with SIGTERM, followed by SIGKILL after the grace period.
+    async fn stop(mut self) -> i32 {
+        self.kill_signal.send(SignalKind::SIGTERM);
+        if let Ok(res) = tokio::time::timeout(STOP_GRACE_PERIOD, &mut self.handle).await {
+            return res.unwrap_or(1);
+        }
+        self.kill_signal.send(SignalKind::SIGKILL);
+        self.handle.await.unwrap_or(1)
+    }
+}
+
+/// Services started by rusk, which may be kept running across the runs of the watch mode
+#[derive(Clone, Default)]
+pub struct Services(Rc<RefCell<HashMap<TaskKey, Service>>>);
+
+impl Services {
+    /// Check if the service is running with the same stamp
+    pub fn is_running(&self, key: &TaskKey, stamp: &Stamp) -> bool {
+        self.0
+            .borrow()
+            .get(key)
+            .is_some_and(|service| !service.handle.is_finished() && service.stamp == *stamp)
+    }
+    /// Check if the service has been started and not stopped by rusk
+    pub fn contains(&self, key: &TaskKey) -> bool {
+        self.0.borrow().contains_key(key)
+    }
+    /// Register a started service
+    pub fn insert(
+        &self,
+        key: TaskKey,
+        kill_signal: KillSignal,
+        handle: JoinHandle<i32>,
+        stamp: Stamp,
+    ) {
+        let service = Service {
+            kill_signal,
+            handle,
+            stamp,
+        };
+        self.0.borrow_mut().insert(key, service);
+    }
+    /// Stop the service if running
+    pub async fn stop(&self, key: &TaskKey) {
+        let service = self.0.borrow_mut().remove(key);
+        if let Some(service) = service {
+            service.stop().await;
+        }
+    }
+    /// Stop the services other than the given ones
+    pub async fn stop_except(&self, keep: &HashSet<&TaskKey>) {
+        let stopped = self
+            .0
+            .borrow_mut()
+            .extract_if(|key, _| !keep.contains(key))
+            .map(|(_, service)| service)
+            .collect::<Vec<_>>();
+        futures::future::join_all(stopped.into_iter().map(Service::stop)).await;
+    }
+    /// Stop all the services
+    pub async fn stop_all(&self) {
+        self.stop_except(&HashSet::new()).await;
+    }
+    /// Wait until any of the given services exits, returning its key and exit code.
+    /// Pending forever if none of them is running.
+    pub async fn wait_any(&self, keys: &[TaskKey]) -> (TaskKey, i32) {
+        loop {
+            for key in keys {
+                let finished = self
+                    .0
+                    .borrow()
+                    .get(key)
+                    .is_some_and(|service| service.handle.is_finished());
+                if finished {
+                    let service = self.0.borrow_mut().remove(key).unwrap(); // Checked above
+                    return (key.clone(), service.handle.await.unwrap_or(1));
+                }
+            }
+            tokio::time::sleep(PROBE_INTERVAL).await;
+        }
+    }
+}
+
+impl ReadyProbe {
+    /// Wait until the service gets ready. `ready` receives a message when the log pattern is matched.
+    pub async fn wait(&self, ready: &mut mpsc::UnboundedReceiver<()>) {
+        match self {
+            ReadyProbe::Port(port) => {
+                while tokio::net::TcpStream::connect(("localhost", *port))
+                    .await
+                    .is_err()
+                {
+                    tokio::time::sleep(PROBE_INTERVAL).await;
+                }
+            }
+            ReadyProbe::Log(_) => {
+                if ready.recv().await.is_none() {
+                    // The output is closed without matching, so it never gets ready
+                    std::future::pending::<()>().await;
+                }
+            }
+            ReadyProbe::File(file) => {
+                while !matches!(tokio::fs::try_exists(file).await, Ok(true)) {
+                    tokio::time::sleep(PROBE_INTERVAL).await;
+                }
+            }
+        }
+    }
+}
+
+/// Forward the output of the service to the writer in the background,
+/// sending a message when a line matches the pattern.
+pub fn scan_output(
+    reader: ShellPipeReader,
+    mut writer: ShellPipeWriter,
+    pattern: Regex,
+    ready: mpsc::UnboundedSender<()>,
+) {
+    /// Adapter to read lines from ShellPipeReader
+    struct PipeRead(ShellPipeReader);
+    impl Read for PipeRead {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.0.read(buf).map_err(std::io::Error::other)
+        }
+    }
+
+    std::thread::spawn(move || {
+        let mut matched = false;
+        for line in BufReader::new(PipeRead(reader)).split(b'\n') {
+            let Ok(mut line) = line else {
+                break;
+            };
+            if !matched && pattern.is_match(&String::from_utf8_lossy(&line)) {
+                matched = true;
+                _ = ready.send(());
+            }
+            line.push(b'\n');
+            if writer.write_all(&line).is_err() {
+                break;
+            }
+        }
+    });
+}
@@ -0,0 +1,149 @@
+//! Persistent state of the tasks, stored under the project root between runs.
+//! The project root is the root of the git repository, so that it is shared wherever rusk runs in it.
+
+use std::{collections::BTreeMap, io, path::Path};
+
+use hashbrown::HashMap;
+use sha2::{Digest, Sha256};
+use tokio::io::AsyncReadExt;
+
+use crate::{path::NormarizedPath, taskkey::TaskKey};
+
+/// Path of the state file relative to the project root, see `get_project_root`
+const STATE_FILE: &str = ".rusk/state";
+
+/// State of the tasks stored in the state file
+#[derive(Default, serde::Serialize, serde::Deserialize)]
+pub struct StateStore {
+    /// Records of the last successful runs, keyed by the phony task name or the absolute path of the target
+    #[serde(default)]
+    tasks: HashMap<String, TaskRecord>,
+    /// Whether the state has been changed since loaded
+    #[serde(skip)]
+    dirty: bool,
+}
+
+/// Key of the task in the state file.
+/// Absolute paths never collide with phony task names because they contain a path separator.
+fn record_key(key: &TaskKey) -> &str {
+    match key {
+        TaskKey::Phony(name) => name.as_ref(),
+        TaskKey::File(path) => path.as_abs_str(),
+    }
+}
+
+impl StateStore {
+    /// Load the state file under the root. A missing or broken file is regarded as empty.
+    pub fn load(root: &Path) -> Self {
+        std::fs::read_to_string(root.join(STATE_FILE))
+            .ok()
+            .and_then(|content| toml::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+    /// Save the state file under the root if the state has been changed.
+    pub fn save(&self, root: &Path) -> io::Result<()> {
+        if !self.dirty {
+            return Ok(());
+        }
+        let path = root.join(STATE_FILE);
+        std::fs::create_dir_all(path.parent().unwrap())?; // STATE_FILE has a parent directory
+        let content = toml::to_string(self).map_err(io::Error::other)?;
+        std::fs::write(path, content)
+    }
+    /// Get the record of the last successful run
+    pub fn get(&self, key: &TaskKey) -> Option<&TaskRecord> {
+        self.tasks.get(record_key(key))
+    }
+    /// Absolute paths of the files produced by the recorded runs
+    pub fn outputs(&self) -> impl Iterator<Item = &str> {
+        self.tasks
+            .values()
+            .flat_map(|record| &record.outputs)
+            .map(String::as_str)
+    }
+    /// Record a successful run
+    pub fn record(&mut self, key: &TaskKey, record: TaskRecord) {
+        if self.get(key) != Some(&record) {
+            self.tasks.insert(record_key(key).to_owned(), record);
+            self.dirty = true;
+        }
+    }
+}
+
+/// What a successful run of a task was made from
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct TaskRecord {
+    /// Absolute paths of the dependency files, sorted
+    pub inputs: Vec<String>,
+    /// Content hashes of the dependency files keyed by the absolute path, recorded only in fingerprint mode
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub hashes: BTreeMap<String, String>,
+    /// Hash of the script
+    pub script: String,
+    /// Hash of the environment variables specific to the task
+    pub envs: String,
+    /// Absolute paths of the files produced by the task
+    pub outputs: Vec<String>,
+}
+
+impl TaskRecord {
+    /// Make the record of a run. The dependency files are hashed only if `hash_inputs` is set.
+    pub async fn new<'a>(
+        inputs: impl IntoIterator<Item = &'a NormarizedPath>,
+        script: Option<&str>,
+        envs: &HashMap<String, String>,
+        outputs: impl IntoIterator<Item = &'a NormarizedPath>,
+        hash_inputs: bool,
+    ) -> io::Result<Self> {
+        let mut paths = Vec::new();
+        let mut hashes = BTreeMap::new();
+        for input in inputs {
+            paths.push(input.as_abs_str().to_owned());
+            if hash_inputs {
+                hashes.insert(input.as_abs_str().to_owned(), hash_file(input).await?);
+            }
+        }
+        paths.sort();
+        paths.dedup();
+        let mut envs_hasher = Sha256::new();
+        let mut envs: Vec<_> = envs.iter().collect();
+        envs.sort();
+        for (name, value) in envs {
+            // NUL never appears in environment variables, so it separates them unambiguously
+            envs_hasher.update(name);
+            envs_hasher.update([0]);
+            envs_hasher.update(value);
+            envs_hasher.update([0]);
+        }
+        Ok(Self {
+            inputs: paths,
+            hashes,
+            script: hex(Sha256::digest(script.unwrap_or_default())),
+            envs: hex(envs_hasher.finalize()),
+            outputs: outputs
+                .into_iter()
+                .map(|output| output.as_abs_str().to_owned())
+                .collect(),
+        })
+    }
+}
+
+/// Hash the content of a file
+async fn hash_file(path: &Path) -> io::Result<String> {
+    let mut file = tokio::fs::File::open(path).await?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0; 0x10000];
+    loop {
+        let n = file.read(&mut buf).await?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(hex(hasher.finalize()))
+}
+
+/// Encode bytes in lowercase hexadecimal
+fn hex(bytes: impl AsRef<[u8]>) -> String {
+    bytes.as_ref().iter().map(|b| format!("{b:02x}")).collect()
+}
@@ -3,7 +3,7 @@
 use std::{
     fmt::{Debug, Display},
     hash::Hash,
-    ops::{Deref, DerefMut},
+    ops::Deref,
     path::Path,
 };
 
@@ -136,14 +136,8 @@ impl<'a> TaskKeyRef<'a> {
     pub fn as_task_key(&self) -> &TaskKey {
         self.owned.deref()
     }
-    pub fn into_task_key(mut self) -> TaskKey {
-        std::mem::replace(self.owned.deref_mut(), unsafe {
-            use std::{
-                alloc::{alloc, Layout},
-                ptr::read,
-            };
-            read(alloc(Layout::new::<TaskKey>()) as *const TaskKey)
-        })
+    pub fn into_task_key(self) -> TaskKey {
+        Lazy::into_value(self.owned).unwrap_or_else(|init| init())
     }
 }
 
@@ -208,6 +202,15 @@ impl TryFrom<String> for TaskKeyRelative {
     }
 }
 
+impl AsRef<str> for TaskKeyRelative {
+    fn as_ref(&self) -> &str {
+        match self {
+            TaskKeyRelative::Phony(phony_name) => phony_name.as_ref(),
+            TaskKeyRelative::File(path) => path.as_ref(),
+        }
+    }
+}
+
 impl TaskKeyRelative {
     pub fn as_task_key<'a>(&'a self, base: &'a Path) -> TaskKeyRef<'a> {
         TaskKeyRef::new(self, base)
@@ -217,6 +220,16 @@ impl TaskKeyRelative {
     }
 }
 
+impl TaskKey {
+    /// Path of the file task, or None for a phony task
+    pub fn as_file(&self) -> Option<&NormarizedPath> {
+        match self {
+            TaskKey::File(file) => Some(file),
+            TaskKey::Phony(_) => None,
+        }
+    }
+}
+
 impl Hash for TaskKey {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
         self.as_ref().hash(state);
@@ -0,0 +1,58 @@
+//! Polling file watcher for the watch mode.
+
+use std::time::{Duration, SystemTime};
+
+use crate::path::NormarizedPath;
+
+/// Interval of polling the files
+const POLL_INTERVAL: Duration = Duration::from_millis(300);
+
+/// Quiet period to wait for after a change, so that a burst of changes is handled at once
+const DEBOUNCE: Duration = Duration::from_millis(100);
+
+/// Watches the modified datetimes of the files
+pub struct Watcher {
+    /// Files to be watched
+    files: Vec<NormarizedPath>,
+    /// Modified datetimes of the files, or None if not found
+    snapshot: Vec<Option<SystemTime>>,
+}
+
+impl Watcher {
+    /// Start watching the files from the current state.
+    pub fn new(files: Vec<NormarizedPath>) -> Self {
+        let snapshot = snapshot(&files);
+        Self { files, snapshot }
+    }
+    /// Number of the files being watched
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+    /// Wait until any of the files is created, modified or removed, and the changes settle down.
+    pub async fn changed(&mut self) {
+        loop {
+            tokio::time::sleep(POLL_INTERVAL).await;
+            let current = snapshot(&self.files);
+            if current != self.snapshot {
+                self.snapshot = current;
+                break;
+            }
+        }
+        loop {
+            tokio::time::sleep(DEBOUNCE).await;
+            let current = snapshot(&self.files);
+            if current == self.snapshot {
+                break;
+            }
+            self.snapshot = current;
+        }
+    }
+}
+
+/// Get the modified datetimes of the files
+fn snapshot(files: &[NormarizedPath]) -> Vec<Option<SystemTime>> {
+    files
+        .iter()
+        .map(|file| std::fs::metadata(file).and_then(|m| m.modified()).ok())
+        .collect()
+}
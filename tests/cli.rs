@@ -0,0 +1,464 @@
+//! Tests running the rusk binary against temporary projects.
+
+use std::{
+    path::{Path, PathBuf},
+    process::{Command, Output},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// Temporary project directory, removed when dropped
+struct Project(PathBuf);
+
+impl Project {
+    /// Create an empty project directory unique to the test
+    fn new(name: &str) -> Self {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+        let dir = std::env::temp_dir().join(format!(
+            "rusk-test-{}-{name}-{}",
+            std::process::id(),
+            COUNT.fetch_add(1, Ordering::Relaxed)
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        Self(dir)
+    }
+    /// Write a file, creating its parent directories
+    fn file(&self, path: &str, content: &str) -> &Self {
+        let path = self.0.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+        self
+    }
+    /// Path of a file in the project
+    fn path(&self, path: &str) -> PathBuf {
+        self.0.join(path)
+    }
+    /// Run rusk in the project
+    fn rusk(&self, args: &[&str]) -> Output {
+        rusk_in(&self.0, args)
+    }
+}
+
+impl Drop for Project {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Run rusk in the directory
+fn rusk_in(dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_rusk"))
+        .current_dir(dir)
+        .args(args)
+        .env_remove("RUSK_JOBS")
+        .env("NO_COLOR", "1")
+        .output()
+        .unwrap()
+}
+
+/// Stdout of a successful run
+fn stdout(output: Output) -> String {
+    assert!(
+        output.status.success(),
+        "rusk failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn input_generated_by_another_task() {
+    let project = Project::new("generated-input");
+    project.file(
+        "rusk.toml",
+        r#"
+[tasks.setup]
+script = "echo setup"
+
+[tasks."out.txt"]
+depends = ["setup"]
+inputs = ["gen.txt"]
+script = "cp gen.txt out.txt"
+
+[tasks."gen.txt"]
+script = "echo generated > gen.txt"
+"#,
+    );
+    stdout(project.rusk(&["out.txt"]));
+    let out = std::fs::read_to_string(project.path("out.txt")).unwrap();
+    assert_eq!(out.trim(), "generated");
+}
+
+#[test]
+fn existing_file_without_applicable_rule_is_source() {
+    let project = Project::new("plain-source");
+    project
+        .file(
+            "rusk.toml",
+            r#"
+[tasks."%.min.js"]
+depends = ["%.js"]
+script = "cp $RUSK_STEM.js $RUSK_STEM.min.js"
+
+[tasks.bundle]
+depends = ["vendor.min.js", "app.min.js"]
+script = "cat vendor.min.js app.min.js"
+"#,
+        )
+        .file("vendor.min.js", "vendor\n")
+        .file("app.js", "app\n");
+    assert_eq!(stdout(project.rusk(&["bundle"])), "vendor\napp\n");
+}
+
+#[test]
+fn glob_respects_anchored_gitignore() {
+    let project = Project::new("anchored-gitignore");
+    std::fs::create_dir(project.path(".git")).unwrap();
+    project
+        .file(".gitignore", "src/sub/ignored.rs\n")
+        .file("src/a.rs", "")
+        .file("src/sub/b.rs", "")
+        .file("src/sub/ignored.rs", "")
+        .file(
+            "rusk.toml",
+            r#"
+[tasks.nested]
+depends = ["src/**/*.rs"]
+
+[tasks.anywhere]
+depends = ["**/*.rs"]
+"#,
+        );
+    let graph = stdout(project.rusk(&["--graph", "nested", "anywhere"]));
+    assert!(graph.contains("src/sub/b.rs"), "{graph}");
+    assert!(!graph.contains("ignored.rs"), "{graph}");
+}
+
+#[test]
+fn clean_lists_files_made_by_rules() {
+    let project = Project::new("clean-rules");
+    project
+        .file(
+            "rusk.toml",
+            r#"
+[tasks."build/%.html"]
+depends = ["src/%.md"]
+script = "cp src/$RUSK_STEM.md build/$RUSK_STEM.html"
+"#,
+        )
+        .file("src/a.md", "")
+        .file("build/orphan.html", "");
+    stdout(project.rusk(&["build/a.html"]));
+    let listed = stdout(project.rusk(&["--clean", "-n"]));
+    assert!(listed.contains("build/a.html"), "{listed}");
+    assert!(!listed.contains("orphan.html"), "{listed}");
+}
+
+#[test]
+fn script_change_noticed_without_previous_record() {
+    let project = Project::new("baseline");
+    project.file("in.txt", "a\n").file(
+        "rusk.toml",
+        r#"
+[tasks."out.txt"]
+depends = ["in.txt"]
+script = "cp in.txt out.txt"
+"#,
+    );
+    stdout(project.rusk(&["out.txt"]));
+    // As if built before the state was recorded
+    std::fs::remove_dir_all(project.path(".rusk")).unwrap();
+    stdout(project.rusk(&["out.txt"]));
+    project.file(
+        "rusk.toml",
+        r#"
+[tasks."out.txt"]
+depends = ["in.txt"]
+script = "cp in.txt out.txt && echo ran"
+"#,
+    );
+    assert_eq!(stdout(project.rusk(&["out.txt"])), "ran\n");
+}
+
+#[test]
+fn dry_run_skips_dependents_of_failure() {
+    let project = Project::new("plan-failure");
+    project.file(
+        "rusk.toml",
+        r#"
+[tasks."app.js"]
+depends = ["missing.ts"]
+script = "cp missing.ts app.js"
+
+[tasks.bundle]
+depends = ["app.js"]
+script = "echo bundle"
+"#,
+    );
+    let output = project.rusk(&["-n", "bundle"]);
+    assert!(!output.status.success());
+    let plan = String::from_utf8(output.stdout).unwrap();
+    assert!(plan.contains("fail app.js"), "{plan}");
+    assert!(plan.contains("skip bundle  app.js fails"), "{plan}");
+}
+
+#[test]
+fn missing_dependency_located_at_run_time() {
+    let project = Project::new("located-dependency");
+    project.file(
+        "rusk.toml",
+        r#"[tasks."app.js"]
+depends = ["missing.ts"]
+script = "cp missing.ts app.js"
+"#,
+    );
+    let output = project.rusk(&["app.js"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("--> rusk.toml:2:12"), "{stderr}");
+}
+
+#[test]
+fn state_stored_at_repository_root() {
+    let project = Project::new("state-root");
+    std::fs::create_dir(project.path(".git")).unwrap();
+    project.file("sub/in.txt", "a\n").file(
+        "sub/rusk.toml",
+        r#"
+[tasks."out.txt"]
+depends = ["in.txt"]
+script = "cp in.txt out.txt"
+"#,
+    );
+    stdout(rusk_in(&project.path("sub"), &["out.txt"]));
+    stdout(project.rusk(&["-C", "sub", "out.txt"]));
+    assert!(project.path(".rusk/state").is_file());
+    assert!(!project.path("sub/.rusk").exists());
+}
+
+#[test]
+fn clean_keeps_ignored_files_matching_rules() {
+    let project = Project::new("clean-vendor");
+    std::fs::create_dir(project.path(".git")).unwrap();
+    project
+        .file(".gitignore", "node_modules/\n")
+        .file("node_modules/lib/x.js", "")
+        .file("node_modules/lib/x.min.js", "")
+        .file("app.js", "")
+        .file(
+            "rusk.toml",
+            r#"
+[tasks."%.min.js"]
+depends = ["%.js"]
+script = "cp $RUSK_STEM.js $RUSK_STEM.min.js"
+"#,
+        );
+    stdout(project.rusk(&["app.min.js"]));
+    stdout(project.rusk(&["--clean"]));
+    assert!(!project.path("app.min.js").exists());
+    assert!(project.path("node_modules/lib/x.min.js").exists());
+}
+
+#[test]
+fn literal_path_with_glob_characters() {
+    let project = Project::new("literal-brackets");
+    project.file("pages/[id].tsx", "a\n").file(
+        "rusk.toml",
+        r#"
+[tasks."out.js"]
+depends = ["pages/[id].tsx"]
+script = "echo built > out.js"
+
+[tasks.snapshots]
+depends = ["snap/*.txt"]
+script = "echo snapshots"
+"#,
+    );
+    stdout(project.rusk(&["out.js"]));
+    // Made newer than the output
+    std::thread::sleep(std::time::Duration::from_millis(20));
+    project.file("pages/[id].tsx", "b\n");
+    let plan = stdout(project.rusk(&["-n", "out.js"]));
+    assert!(plan.contains("run  out.js"), "{plan}");
+
+    let output = project.rusk(&["snapshots"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("the glob pattern matches no file"),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn mermaid_class_per_statement() {
+    let project = Project::new("mermaid");
+    project.file("in.txt", "").file(
+        "rusk.toml",
+        r#"
+[tasks."out.txt"]
+depends = ["in.txt"]
+script = "cp in.txt out.txt"
+"#,
+    );
+    stdout(project.rusk(&["out.txt"]));
+    let graph = stdout(project.rusk(&["--graph=mermaid", "out.txt"]));
+    let classes = graph
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("class "))
+        .collect::<Vec<_>>();
+    assert!(!classes.is_empty(), "{graph}");
+    for class in classes {
+        // Nodes are separated by commas, followed by a single class name
+        let (_, name) = class.split_once(' ').unwrap();
+        assert!(
+            ["phony", "file", "source", "fresh"].contains(&name),
+            "{graph}"
+        );
+    }
+    assert!(graph.contains(" fresh\n"), "{graph}");
+}
+
+#[test]
+fn rule_in_directory_containing_percent() {
+    let project = Project::new("100%");
+    project.file("a.c", "a\n").file("common.h", "h\n").file(
+        "rusk.toml",
+        r#"
+[tasks."%.o"]
+depends = ["%.c", "common.h"]
+script = "cat $RUSK_STEM.c common.h > $RUSK_STEM.o"
+"#,
+    );
+    stdout(project.rusk(&["a.o"]));
+    let out = std::fs::read_to_string(project.path("a.o")).unwrap();
+    assert_eq!(out, "a\nh\n");
+}
+
+#[test]
+fn watch_rejects_other_modes() {
+    let project = Project::new("watch-conflict");
+    project.file("rusk.toml", "[tasks.a]\nscript = \"echo a\"\n");
+    for (args, other) in [
+        (&["-w", "-n", "a"][..], "--dry-run"),
+        (&["-w", "--clean"], "--clean"),
+        (&["--graph", "-w", "a"], "--graph"),
+        (&["-w", "--check"], "--check"),
+    ] {
+        let output = project.rusk(args);
+        assert!(!output.status.success());
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        let expected = format!("Option --watch cannot be combined with {other}");
+        assert!(stderr.contains(&expected), "{stderr}");
+    }
+}
+
+#[test]
+fn grouped_short_flags() {
+    let project = Project::new("grouped-flags");
+    project.file("rusk.toml", "[tasks.a]\nscript = \"echo a\"\n");
+    // Same as `-k -n -j 2`
+    let plan = stdout(project.rusk(&["-knj2", "a"]));
+    assert!(plan.starts_with("run  a"), "{plan}");
+}
+
+#[test]
+fn option_values_and_parameters() {
+    let project = Project::new("grammar");
+    project.file(
+        "sub/rusk.toml",
+        r#"
+[tasks.greet]
+args = [{ name = "name", default = "world" }]
+script = "echo hello $name $RUSK_ARGS"
+"#,
+    );
+    for args in [
+        &["--directory=sub", "greet"][..],
+        &["--directory", "sub", "greet"],
+        &["-Csub", "greet"],
+        &["greet", "-C", "sub"],
+    ] {
+        assert_eq!(stdout(project.rusk(args)), "hello world\n", "{args:?}");
+    }
+    let output = project.rusk(&["-C", "sub", "greet", "name=rusk", "--", "-n", "x=1"]);
+    // Options and parameters after `--` are passed through
+    assert_eq!(stdout(output), "hello rusk -n x=1\n");
+
+    let output = project.rusk(&["-C", "sub", "name=rusk", "greet"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("Parameter \"name=rusk\" must follow a task name"),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn disjoint_cycles_reported_together() {
+    let project = Project::new("cycles");
+    project.file(
+        "rusk.toml",
+        r#"
+[tasks.a]
+depends = ["b"]
+
+[tasks.b]
+depends = ["a"]
+
+[tasks.c]
+depends = ["d"]
+
+[tasks.d]
+depends = ["c"]
+"#,
+    );
+    let output = project.rusk(&["a", "c"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("2 cycle(s) found"), "{stderr}");
+    assert!(stderr.contains("a -> b -> a"), "{stderr}");
+    assert!(stderr.contains("c -> d -> c"), "{stderr}");
+}
+
+/// Failing task and its slower sibling, both needed by `all`
+const SIBLINGS: &str = r#"
+[tasks.fail]
+script = "sleep 0.3 && exit 3"
+
+[tasks.slow]
+script = "sleep 1 && echo finished > slow.txt"
+
+[tasks.all]
+depends = ["fail", "slow"]
+script = "echo all"
+"#;
+
+#[test]
+fn keep_going_finishes_independent_sibling() {
+    let project = Project::new("keep-going");
+    project.file("rusk.toml", SIBLINGS);
+    let output = project.rusk(&["-k", "-j2", "all"]);
+    assert_eq!(output.status.code(), Some(3));
+    assert!(project.path("slow.txt").exists());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("Skipped because of the failures: all"),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn running_sibling_interrupted() {
+    let project = Project::new("interrupted");
+    project.file("rusk.toml", SIBLINGS);
+    let output = project.rusk(&["-j2", "all"]);
+    assert_eq!(output.status.code(), Some(3));
+    assert!(!project.path("slow.txt").exists());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("Interrupted: slow"), "{stderr}");
+    assert!(
+        stderr.contains("Skipped because of the failures: all"),
+        "{stderr}"
+    );
+}